@@ -1,8 +1,15 @@
+use crate::chart::alarm::AlarmEvent;
 use crate::communicationtrait::{CommunicationEvent, CommunicationManager};
+use crate::framing::{FrameSplitter, FramingMode, FramingSettings};
 use crate::generalsettings::AppSettings;
-use crate::gui::{ChartPanel, ConnectionPanel, FileLogPanel, MenuBar, RxPanel, SendPanel};
+use crate::gui::send_panel::send_to_manager;
+use crate::gui::{
+    ChartPanel, ConnectionPanel, FileLogPanel, FileSendPanel, MacroPanel, MenuBar, RepeatPanel,
+    RxPanel, SendPanel, TransferPanel,
+};
 use crate::serial_impl::SerialCommunication;
 use std::sync::{mpsc, Arc, Mutex};
+use std::time::Instant;
 
 use crate::info::info_popup;
 use crate::update::{check_new_version, update_popup};
@@ -20,13 +27,24 @@ pub struct TemplateApp {
     #[serde(skip)]
     menu_bar: MenuBar,
     send_panel: SendPanel,
+    #[serde(skip)]
+    macro_panel: MacroPanel,
+    repeat_panel: RepeatPanel,
     file_log_panel: FileLogPanel,
+    file_send_panel: FileSendPanel,
+    transfer_panel: TransferPanel,
 
     // Core state
     #[serde(skip)]
     serial_manager: Option<Box<dyn CommunicationManager>>,
     #[serde(skip)]
     serial_events_rx: Option<mpsc::Receiver<CommunicationEvent>>,
+    /// Groups received chunks into packets for the views.
+    #[serde(skip)]
+    framer: FrameSplitter,
+    /// Settings the framer was last built from.
+    #[serde(skip)]
+    framing_config: FramingSettings,
 
     #[serde(skip)]
     show_info_popup: bool,
@@ -49,9 +67,15 @@ impl Default for TemplateApp {
             chart_panel: ChartPanel::new(settings.max_log_string_length),
             menu_bar: MenuBar::new(),
             send_panel: SendPanel::new(),
+            macro_panel: MacroPanel::new(),
+            repeat_panel: RepeatPanel::new(),
             file_log_panel: FileLogPanel::new(default_filename),
+            file_send_panel: FileSendPanel::new(),
+            transfer_panel: TransferPanel::new(),
             serial_manager: Some(Box::new(SerialCommunication::new())),
             serial_events_rx: None,
+            framer: FrameSplitter::default(),
+            framing_config: FramingSettings::default(),
             show_info_popup: false,
             show_update_popup: Arc::new(Mutex::new(false)),
         }
@@ -69,6 +93,7 @@ impl TemplateApp {
 
         app.connection_panel = ConnectionPanel::new();
         app.menu_bar = MenuBar::new();
+        app.macro_panel = MacroPanel::new();
         app.serial_manager = Some(Box::new(SerialCommunication::new()));
         app.serial_events_rx = None;
         app.show_info_popup = false;
@@ -106,6 +131,7 @@ impl TemplateApp {
     fn handle_serial_events(&mut self, ctx: &egui::Context) {
         self.connection_panel
             .update_button_text(&self.serial_manager);
+        self.update_framer();
 
         let mut events = Vec::new();
         if let Some(ref rx) = self.serial_events_rx {
@@ -116,22 +142,28 @@ impl TemplateApp {
 
         for event in events {
             match event {
-                CommunicationEvent::DataReceived(data) => {
-                    let message = if self.settings.byte_mode {
-                        // Convert bytes to hex string representation with packet separator
-                        let hex_string = data
-                            .iter()
-                            .map(|byte| format!("{byte:02X}"))
-                            .collect::<Vec<String>>()
-                            .join(" ");
-                        format!("{hex_string} ")
-                    } else {
-                        String::from_utf8_lossy(&data).into_owned()
-                    };
-                    self.write_log(&message);
+                CommunicationEvent::DataReceived(data, received) => {
+                    // A running file transfer owns the byte stream
+                    if self.transfer_panel.is_active() {
+                        self.transfer_panel.handle_data(&data);
+                        ctx.request_repaint();
+                        continue;
+                    }
+                    let mut data = data;
+                    if let Some(start) = self.transfer_panel.detect_incoming(&data) {
+                        // What came before the ZMODEM offer is shown as usual
+                        data.truncate(start);
+                        ctx.request_repaint();
+                    }
+                    let data = self.transfer_panel.strip_trailer(data);
+                    if data.is_empty() {
+                        continue;
+                    }
+
+                    // The log file gets the bytes as received, the views whole packets
                     self.file_log_panel.write_to_file(&data);
-                    if self.settings.show_chart_panel {
-                        self.chart_panel.process_rx(data);
+                    for frame in self.framer.push(&data, received) {
+                        self.handle_frame(ctx, frame, received);
                     }
                     ctx.request_repaint();
                 }
@@ -144,6 +176,110 @@ impl TemplateApp {
                 }
             }
         }
+
+        // A packet ended by an idle gap is complete once nothing more came
+        let now = Instant::now();
+        if let Some(frame) = self.framer.poll(now) {
+            self.handle_frame(ctx, frame, now);
+            ctx.request_repaint();
+        }
+        if let Some(deadline) = self.framer.pending_deadline() {
+            ctx.request_repaint_after(deadline.saturating_duration_since(now));
+        }
+        // Binary chart frames waiting in vain for the next sync header
+        if self.settings.show_chart_panel || !self.settings.chart_thresholds.is_empty() {
+            for alarm in self.chart_panel.poll(now, &self.settings) {
+                self.handle_alarm(ctx, &alarm);
+            }
+            if let Some(deadline) = self.chart_panel.pending_deadline(&self.settings) {
+                ctx.request_repaint_after(deadline.saturating_duration_since(now));
+            }
+        }
+    }
+
+    /// Rebuild the framer when its settings changed. Invalid settings keep
+    /// the previous framer; the settings panel shows the error.
+    fn update_framer(&mut self) {
+        if self.settings.framing == self.framing_config {
+            return;
+        }
+        if let Ok(framer) = FrameSplitter::new(&self.settings.framing) {
+            self.framer = framer;
+        }
+        self.framing_config = self.settings.framing.clone();
+    }
+
+    /// Show a received packet in the text and chart panels.
+    fn handle_frame(&mut self, ctx: &egui::Context, frame: Vec<u8>, received: Instant) {
+        let message = if self.settings.byte_mode {
+            // Convert bytes to hex string representation with packet separator
+            let hex_string = frame
+                .iter()
+                .map(|byte| format!("{byte:02X}"))
+                .collect::<Vec<String>>()
+                .join(" ");
+            if self.settings.framing.mode == FramingMode::None {
+                format!("{hex_string} ")
+            } else {
+                // One line per packet
+                format!("{hex_string}\n")
+            }
+        } else {
+            String::from_utf8_lossy(&frame).into_owned()
+        };
+        self.write_log(&message);
+        // Thresholds are watched while the chart is hidden too
+        if self.settings.show_chart_panel || !self.settings.chart_thresholds.is_empty() {
+            for alarm in self.chart_panel.process_rx(frame, received, &self.settings) {
+                self.handle_alarm(ctx, &alarm);
+            }
+        }
+    }
+
+    /// Run the actions of a chart threshold that was crossed. Highlighting
+    /// and pausing are done by the chart itself.
+    fn handle_alarm(&mut self, ctx: &egui::Context, alarm: &AlarmEvent) {
+        use chrono::prelude::Local;
+
+        let threshold = &alarm.threshold;
+        let time = Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
+        if threshold.log {
+            self.write_alarm_marker(&format!("ALARM {time}: {alarm}"));
+        }
+        if threshold.sound {
+            crate::beep::beep();
+            ctx.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(
+                egui::UserAttentionType::Critical,
+            ));
+        }
+        if !threshold.send_macro.is_empty() {
+            match self
+                .settings
+                .macros
+                .iter()
+                .find(|macro_def| macro_def.name == threshold.send_macro)
+                .map(|macro_def| macro_def.to_bytes())
+            {
+                Some(Ok(data)) => {
+                    send_to_manager(&mut self.serial_manager, data);
+                }
+                Some(Err(e)) => self.write_alarm_marker(&format!(
+                    "ALARM {time}: macro \"{}\" not sent: {e}",
+                    threshold.send_macro
+                )),
+                None => self.write_alarm_marker(&format!(
+                    "ALARM {time}: macro \"{}\" not found",
+                    threshold.send_macro
+                )),
+            }
+        }
+    }
+
+    /// Write an alarm line to the RX panel and the log file.
+    fn write_alarm_marker(&mut self, text: &str) {
+        let marker = format!("\n*** {text} ***\n");
+        self.write_log(&marker);
+        self.file_log_panel.write_to_file(marker.as_bytes());
     }
 
     fn write_log(&mut self, message: &str) {
@@ -160,13 +296,23 @@ impl eframe::App for TemplateApp {
             ctx,
             || {
                 self.rx_panel.clear();
+                self.framer.clear();
             },
             || {
                 self.show_info_popup = true;
             },
+            || {
+                self.file_send_panel.open = true;
+            },
+            || {
+                self.transfer_panel.open = true;
+            },
             &mut self.settings,
         );
 
+        self.file_send_panel.show(ctx, &mut self.serial_manager);
+        self.transfer_panel.show(ctx, &mut self.serial_manager);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             let available_size = ui.available_size();
             let mut chart_area = available_size;
@@ -175,8 +321,12 @@ impl eframe::App for TemplateApp {
             }
 
             if self.settings.show_chart_panel {
-                self.chart_panel
-                    .show(ui, chart_area, self.settings.auto_scroll_log);
+                self.chart_panel.show(
+                    ui,
+                    chart_area,
+                    self.settings.auto_scroll_log,
+                    &mut self.settings,
+                );
                 ui.separator();
             }
 
@@ -186,13 +336,28 @@ impl eframe::App for TemplateApp {
                 ui.separator();
             }
 
-            self.connection_panel
-                .show(ui, &mut self.serial_manager, &mut self.serial_events_rx);
+            if self
+                .connection_panel
+                .show(ui, &mut self.serial_manager, &mut self.serial_events_rx)
+            {
+                // A packet left over from the previous connection is not continued
+                self.framer.clear();
+            }
 
             self.file_log_panel.show(ui, &self.settings);
 
+            self.macro_panel
+                .show(ui, &mut self.serial_manager, &mut self.settings);
+
             self.send_panel
                 .show(ui, &mut self.serial_manager, available_size, &self.settings);
+
+            self.repeat_panel.show(
+                ui,
+                &mut self.serial_manager,
+                self.send_panel.encoded(),
+                &self.settings.macros,
+            );
         });
 
         self.handle_serial_events(ctx);
@@ -0,0 +1,69 @@
+//! Audible alert through the desktop's own alert sound.
+
+/// Freedesktop theme sound played on Linux and the BSDs.
+#[cfg(all(unix, not(target_os = "macos")))]
+const BELL_SOUND: &str = "/usr/share/sounds/freedesktop/stereo/bell.oga";
+
+/// Players tried in order, with their arguments.
+#[cfg(all(unix, not(target_os = "macos")))]
+const PLAYERS: [(&str, &[&str]); 3] = [
+    ("canberra-gtk-play", &["--id=bell"]),
+    ("paplay", &[BELL_SOUND]),
+    ("pw-play", &[BELL_SOUND]),
+];
+
+/// Play the system alert sound without blocking.
+#[cfg(windows)]
+pub fn beep() {
+    use windows_sys::Win32::System::Diagnostics::Debug::MessageBeep;
+    use windows_sys::Win32::UI::WindowsAndMessaging::MB_ICONEXCLAMATION;
+
+    // SAFETY: MessageBeep only queues the sound and takes no pointers
+    unsafe {
+        MessageBeep(MB_ICONEXCLAMATION);
+    }
+}
+
+/// Play the system alert sound without blocking.
+#[cfg(target_os = "macos")]
+pub fn beep() {
+    #[link(name = "AppKit", kind = "framework")]
+    extern "C" {
+        fn NSBeep();
+    }
+
+    // SAFETY: NSBeep takes no arguments and may be called from any thread
+    unsafe {
+        NSBeep();
+    }
+}
+
+/// Play the desktop's bell sound without blocking. Desktops have no common
+/// beep call, so the sound is handed to the first player that is installed;
+/// without one the terminal bell is rung.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn beep() {
+    use std::process::{Command, Stdio};
+
+    std::thread::spawn(|| {
+        for (program, args) in PLAYERS {
+            let played = Command::new(program)
+                .args(args)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success());
+            if played {
+                return;
+            }
+        }
+        eprint!("\x07");
+    });
+}
+
+/// Ring the terminal bell where no alert sound is available.
+#[cfg(not(any(windows, unix)))]
+pub fn beep() {
+    eprint!("\x07");
+}
@@ -0,0 +1,209 @@
+use super::ChartData;
+use serde::{Deserialize, Serialize};
+
+/// Side of a threshold on which a value raises an alarm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ThresholdCondition {
+    #[default]
+    Above,
+    Below,
+}
+
+impl ThresholdCondition {
+    pub const ALL: [ThresholdCondition; 2] = [ThresholdCondition::Above, ThresholdCondition::Below];
+}
+
+impl std::fmt::Display for ThresholdCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThresholdCondition::Above => write!(f, "above"),
+            ThresholdCondition::Below => write!(f, "below"),
+        }
+    }
+}
+
+/// A horizontal limit line on a series and what to do when a value crosses
+/// it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Threshold {
+    /// Name of the series the limit applies to.
+    pub series: String,
+    pub level: f64,
+    pub condition: ThresholdCondition,
+    /// Mark the plot while the limit is exceeded.
+    pub highlight: bool,
+    /// Freeze the chart view.
+    pub pause: bool,
+    /// Write a marker line to the RX panel and the log file.
+    pub log: bool,
+    /// Play the system alert sound and flash the window's taskbar entry.
+    pub sound: bool,
+    /// Name of the macro sent to the device. Empty sends nothing.
+    pub send_macro: String,
+}
+
+impl Default for Threshold {
+    fn default() -> Self {
+        Self {
+            series: String::new(),
+            level: 0.0,
+            condition: ThresholdCondition::Above,
+            highlight: true,
+            pause: false,
+            log: true,
+            sound: false,
+            send_macro: String::new(),
+        }
+    }
+}
+
+impl Threshold {
+    pub fn is_exceeded(&self, value: f64) -> bool {
+        match self.condition {
+            ThresholdCondition::Above => value > self.level,
+            ThresholdCondition::Below => value < self.level,
+        }
+    }
+}
+
+/// A threshold crossed by a received value.
+pub struct AlarmEvent {
+    pub threshold: Threshold,
+    pub value: f64,
+}
+
+impl std::fmt::Display for AlarmEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} = {} {} {}",
+            self.threshold.series, self.value, self.threshold.condition, self.threshold.level
+        )
+    }
+}
+
+/// Tracks which thresholds are exceeded, so an alarm is raised once when a
+/// series crosses its limit and not again until it came back.
+#[derive(Default)]
+pub struct AlarmMonitor {
+    exceeded: Vec<bool>,
+}
+
+impl AlarmMonitor {
+    /// Check the latest record of `data` against `thresholds` and return the
+    /// alarms raised by it. Series without a value in the record keep their
+    /// state.
+    pub fn check(&mut self, thresholds: &[Threshold], data: &ChartData) -> Vec<AlarmEvent> {
+        self.exceeded.resize(thresholds.len(), false);
+
+        let mut events = Vec::new();
+        for (threshold, exceeded) in thresholds.iter().zip(&mut self.exceeded) {
+            let Some(value) = data
+                .series
+                .iter()
+                .find(|series| series.name == threshold.series)
+                .and_then(|series| series.values.back().copied())
+                .filter(|value| value.is_finite())
+            else {
+                continue;
+            };
+
+            let now_exceeded = threshold.is_exceeded(value);
+            if now_exceeded && !*exceeded {
+                events.push(AlarmEvent {
+                    threshold: threshold.clone(),
+                    value,
+                });
+            }
+            *exceeded = now_exceeded;
+        }
+        events
+    }
+
+    /// Whether the threshold at `index` is currently exceeded.
+    pub fn is_exceeded(&self, index: usize) -> bool {
+        self.exceeded.get(index).copied().unwrap_or(false)
+    }
+
+    pub fn clear(&mut self) {
+        self.exceeded.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn threshold(condition: ThresholdCondition) -> Threshold {
+        Threshold {
+            series: "Series 1".to_string(),
+            level: 1.0,
+            condition,
+            ..Threshold::default()
+        }
+    }
+
+    /// Push each value as a record and count the alarms raised by it.
+    fn alarms(thresholds: &[Threshold], values: &[f64]) -> Vec<usize> {
+        let mut data = ChartData::new(100);
+        let mut monitor = AlarmMonitor::default();
+        values
+            .iter()
+            .map(|&value| {
+                data.push_record(&[value], 0.0);
+                monitor.check(thresholds, &data).len()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn crossing_fires_once() {
+        let above = [threshold(ThresholdCondition::Above)];
+        assert_eq!(alarms(&above, &[0.0, 2.0, 3.0, 2.0]), [0, 1, 0, 0]);
+        // Reaching the level is not exceeding it
+        assert_eq!(alarms(&above, &[0.0, 1.0]), [0, 0]);
+
+        let below = [threshold(ThresholdCondition::Below)];
+        assert_eq!(alarms(&below, &[2.0, 0.0, -1.0]), [0, 1, 0]);
+    }
+
+    #[test]
+    fn rearms_after_coming_back() {
+        let above = [threshold(ThresholdCondition::Above)];
+        assert_eq!(alarms(&above, &[2.0, 0.0, 2.0, 1.0, 2.0]), [1, 0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn gaps_keep_the_state() {
+        let above = [threshold(ThresholdCondition::Above)];
+        let nan = f64::NAN;
+        assert_eq!(alarms(&above, &[2.0, nan, 2.0]), [1, 0, 0]);
+        assert_eq!(alarms(&above, &[0.0, nan, nan, 0.0]), [0, 0, 0, 0]);
+        assert_eq!(alarms(&above, &[2.0, nan, 0.0, nan, 2.0]), [1, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn event_names_series_and_value() {
+        let mut data = ChartData::new(100);
+        data.push_record(&[0.0, 5.0], 0.0);
+        let mut monitor = AlarmMonitor::default();
+        let thresholds = [
+            threshold(ThresholdCondition::Above),
+            Threshold {
+                series: "Series 2".to_string(),
+                ..threshold(ThresholdCondition::Above)
+            },
+            Threshold {
+                series: "Missing".to_string(),
+                ..threshold(ThresholdCondition::Below)
+            },
+        ];
+        let events = monitor.check(&thresholds, &data);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].to_string(), "Series 2 = 5 above 1");
+        assert!(!monitor.is_exceeded(0));
+        assert!(monitor.is_exceeded(1));
+        assert!(!monitor.is_exceeded(2));
+    }
+}
@@ -0,0 +1,427 @@
+use crate::checksum::{ByteOrder, Trailer};
+use crate::payload::parse_hex;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Without a checksum, a frame waiting for the next sync header is accepted
+/// anyway once no byte arrived for this long.
+pub const IDLE_GAP: Duration = Duration::from_millis(50);
+
+/// Encoding of one field of a binary frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FieldType {
+    U8,
+    I8,
+    U16,
+    #[default]
+    I16,
+    U32,
+    I32,
+    F32,
+    F64,
+}
+
+impl FieldType {
+    pub const ALL: [FieldType; 8] = [
+        FieldType::U8,
+        FieldType::I8,
+        FieldType::U16,
+        FieldType::I16,
+        FieldType::U32,
+        FieldType::I32,
+        FieldType::F32,
+        FieldType::F64,
+    ];
+
+    pub fn size(&self) -> usize {
+        match self {
+            FieldType::U8 | FieldType::I8 => 1,
+            FieldType::U16 | FieldType::I16 => 2,
+            FieldType::U32 | FieldType::I32 | FieldType::F32 => 4,
+            FieldType::F64 => 8,
+        }
+    }
+
+    /// Decode `bytes`, which must be exactly [`FieldType::size`] long.
+    fn decode(&self, bytes: &[u8], order: ByteOrder) -> f64 {
+        let mut raw = [0u8; 8];
+        raw[..bytes.len()].copy_from_slice(bytes);
+        if order == ByteOrder::BigEndian {
+            raw[..bytes.len()].reverse();
+        }
+        // `raw` now holds the value little endian, zero padded
+        match self {
+            FieldType::U8 => raw[0] as f64,
+            FieldType::I8 => raw[0] as i8 as f64,
+            FieldType::U16 => u16::from_le_bytes([raw[0], raw[1]]) as f64,
+            FieldType::I16 => i16::from_le_bytes([raw[0], raw[1]]) as f64,
+            FieldType::U32 => u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            FieldType::I32 => i32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            FieldType::F32 => f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            FieldType::F64 => f64::from_le_bytes(raw),
+        }
+    }
+}
+
+impl std::fmt::Display for FieldType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldType::U8 => write!(f, "u8"),
+            FieldType::I8 => write!(f, "i8"),
+            FieldType::U16 => write!(f, "u16"),
+            FieldType::I16 => write!(f, "i16"),
+            FieldType::U32 => write!(f, "u32"),
+            FieldType::I32 => write!(f, "i32"),
+            FieldType::F32 => write!(f, "f32"),
+            FieldType::F64 => write!(f, "f64"),
+        }
+    }
+}
+
+/// One value of a binary frame, plotted as its own series. The plotted
+/// value is `raw * scale + offset`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BinaryField {
+    pub name: String,
+    pub field_type: FieldType,
+    pub byte_order: ByteOrder,
+    pub scale: f64,
+    pub offset: f64,
+}
+
+impl Default for BinaryField {
+    fn default() -> Self {
+        Self {
+            name: "ch1".to_string(),
+            field_type: FieldType::I16,
+            byte_order: ByteOrder::LittleEndian,
+            scale: 1.0,
+            offset: 0.0,
+        }
+    }
+}
+
+/// Layout of a binary frame: a sync header followed by the fields, in order,
+/// and optionally a checksum in the last bytes of the frame.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BinaryFrameFormat {
+    /// Hex bytes every frame starts with, e.g. `AA 55`.
+    pub sync: String,
+    /// Total frame length including the header and the checksum. 0 means
+    /// header, fields and checksum; a larger value skips the bytes between
+    /// the fields and the checksum.
+    pub frame_length: usize,
+    pub fields: Vec<BinaryField>,
+    /// Check value ending every frame. Without one, a frame is only accepted
+    /// once the sync header of the next frame follows it, or after
+    /// [`IDLE_GAP`] without data.
+    pub checksum: Trailer,
+}
+
+impl Default for BinaryFrameFormat {
+    fn default() -> Self {
+        Self {
+            sync: "AA 55".to_string(),
+            frame_length: 0,
+            fields: vec![BinaryField::default()],
+            checksum: Trailer::default(),
+        }
+    }
+}
+
+/// Finds frames in the byte stream and decodes their fields.
+pub struct BinaryDecoder {
+    sync: Vec<u8>,
+    frame_length: usize,
+    fields: Vec<BinaryField>,
+    checksum: Trailer,
+    buffer: Vec<u8>,
+}
+
+impl BinaryDecoder {
+    pub fn new(format: &BinaryFrameFormat) -> Result<Self, String> {
+        let sync = parse_hex(&format.sync).map_err(|e| format!("Sync header: {e}"))?;
+        if sync.is_empty() {
+            return Err("Sync header must not be empty".to_string());
+        }
+        if format.fields.is_empty() {
+            return Err("Binary frame has no fields".to_string());
+        }
+
+        let minimum = sync.len()
+            + format
+                .fields
+                .iter()
+                .map(|field| field.field_type.size())
+                .sum::<usize>()
+            + format.checksum.kind.width();
+        let frame_length = match format.frame_length {
+            0 => minimum,
+            length if length < minimum => {
+                return Err(format!(
+                    "Frame length {length} is shorter than header, fields and checksum \
+                     ({minimum} bytes)"
+                ))
+            }
+            length => length,
+        };
+
+        Ok(Self {
+            sync,
+            frame_length,
+            fields: format.fields.clone(),
+            checksum: format.checksum,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Add received bytes and return the decoded frames as `(field name,
+    /// value)` pairs. The second value counts the bytes skipped while looking
+    /// for a sync header.
+    ///
+    /// A sync match is only trusted when the frame's checksum is right or,
+    /// without a checksum, when the next frame's sync header follows it.
+    /// Otherwise the match was a value that looked like a header: one byte is
+    /// dropped and the search goes on from there.
+    pub fn push(&mut self, data: &[u8]) -> (Vec<Vec<(String, f64)>>, usize) {
+        self.buffer.extend_from_slice(data);
+
+        let mut frames = Vec::new();
+        let mut skipped = 0;
+        loop {
+            let Some(start) = self
+                .buffer
+                .windows(self.sync.len())
+                .position(|window| window == self.sync)
+            else {
+                // Keep a possible partial header at the end
+                let keep = self.buffer.len().min(self.sync.len() - 1);
+                skipped += self.buffer.len() - keep;
+                self.buffer.drain(..self.buffer.len() - keep);
+                break;
+            };
+            skipped += start;
+            self.buffer.drain(..start);
+
+            let valid = if self.checksum.is_enabled() {
+                if self.buffer.len() < self.frame_length {
+                    break;
+                }
+                self.checksum.verify(&self.buffer[..self.frame_length])
+            } else {
+                let next = self.frame_length..self.frame_length + self.sync.len();
+                let Some(next_sync) = self.buffer.get(next) else {
+                    break;
+                };
+                next_sync == self.sync.as_slice()
+            };
+            if !valid {
+                skipped += 1;
+                self.buffer.drain(..1);
+                continue;
+            }
+
+            let frame: Vec<u8> = self.buffer.drain(..self.frame_length).collect();
+            frames.push(self.decode(&frame));
+        }
+        (frames, skipped)
+    }
+
+    /// Whether a complete frame waits for the next sync header to confirm it.
+    pub fn is_holding(&self) -> bool {
+        !self.checksum.is_enabled()
+            && self.buffer.len() >= self.frame_length
+            && self.buffer.starts_with(&self.sync)
+    }
+
+    /// Accept the frame waiting for the next sync header, for when the
+    /// stream went quiet after it.
+    pub fn flush(&mut self) -> Option<Vec<(String, f64)>> {
+        if !self.is_holding() {
+            return None;
+        }
+        let frame: Vec<u8> = self.buffer.drain(..self.frame_length).collect();
+        Some(self.decode(&frame))
+    }
+
+    fn decode(&self, frame: &[u8]) -> Vec<(String, f64)> {
+        let mut position = self.sync.len();
+        self.fields
+            .iter()
+            .map(|field| {
+                let size = field.field_type.size();
+                let raw = field
+                    .field_type
+                    .decode(&frame[position..position + size], field.byte_order);
+                position += size;
+                (field.name.clone(), raw * field.scale + field.offset)
+            })
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksum::ChecksumKind;
+
+    fn field(name: &str, field_type: FieldType, byte_order: ByteOrder) -> BinaryField {
+        BinaryField {
+            name: name.to_string(),
+            field_type,
+            byte_order,
+            ..BinaryField::default()
+        }
+    }
+
+    fn decoder(fields: Vec<BinaryField>, checksum: Trailer) -> BinaryDecoder {
+        BinaryDecoder::new(&BinaryFrameFormat {
+            sync: "AA 55".to_string(),
+            frame_length: 0,
+            fields,
+            checksum,
+        })
+        .unwrap()
+    }
+
+    fn values(frame: &[(String, f64)]) -> Vec<f64> {
+        frame.iter().map(|(_, value)| *value).collect()
+    }
+
+    #[test]
+    fn mixed_byte_order() {
+        let mut decoder = decoder(
+            vec![
+                field("a", FieldType::U16, ByteOrder::BigEndian),
+                field("b", FieldType::I32, ByteOrder::LittleEndian),
+                field("c", FieldType::F64, ByteOrder::BigEndian),
+            ],
+            Trailer::default(),
+        );
+        let mut frame = vec![0xAA, 0x55, 0x12, 0x34];
+        frame.extend_from_slice(&(-100_000i32).to_le_bytes());
+        frame.extend_from_slice(&2.5f64.to_be_bytes());
+        let mut stream = frame.clone();
+        stream.extend_from_slice(&frame);
+
+        let (frames, skipped) = decoder.push(&stream);
+        assert_eq!(skipped, 0);
+        // The second frame waits for the header after it
+        assert_eq!(frames.len(), 1);
+        assert_eq!(values(&frames[0]), [4660.0, -100_000.0, 2.5]);
+    }
+
+    #[test]
+    fn signed_fields() {
+        let mut decoder = decoder(
+            vec![
+                field("a", FieldType::I8, ByteOrder::LittleEndian),
+                field("b", FieldType::U8, ByteOrder::LittleEndian),
+                field("c", FieldType::F32, ByteOrder::LittleEndian),
+                field("d", FieldType::F32, ByteOrder::BigEndian),
+            ],
+            Trailer::default(),
+        );
+        let mut frame = vec![0xAA, 0x55, 0xFF, 0xFF];
+        frame.extend_from_slice(&(-1.5f32).to_le_bytes());
+        frame.extend_from_slice(&(-0.25f32).to_be_bytes());
+        frame.extend_from_slice(&[0xAA, 0x55]);
+
+        let (frames, _) = decoder.push(&frame);
+        assert_eq!(values(&frames[0]), [-1.0, 255.0, -1.5, -0.25]);
+    }
+
+    #[test]
+    fn frames_split_across_chunks() {
+        let mut decoder = decoder(
+            vec![field("a", FieldType::I16, ByteOrder::LittleEndian)],
+            Trailer::default(),
+        );
+        let stream = [
+            0x00, 0xAA, 0x55, 0x01, 0x00, 0xAA, 0x55, 0xFE, 0xFF, 0xAA, 0x55,
+        ];
+
+        let mut decoded = Vec::new();
+        let mut skipped = 0;
+        for byte in stream {
+            let (frames, count) = decoder.push(&[byte]);
+            decoded.extend(frames.iter().map(|frame| frame[0].1));
+            skipped += count;
+        }
+        assert_eq!(decoded, [1.0, -2.0]);
+        assert_eq!(skipped, 1);
+    }
+
+    #[test]
+    fn false_sync_in_a_value_is_skipped() {
+        let mut decoder = decoder(
+            vec![field("a", FieldType::U16, ByteOrder::BigEndian)],
+            Trailer::default(),
+        );
+        // Joined mid-frame: the value 12 AA and the header after it look like
+        // a frame starting with AA 55
+        let stream = [
+            0x55, 0x12, 0xAA, 0x55, 0xAA, 0x55, 0x00, 0x01, 0xAA, 0x55, 0x00, 0x02, 0xAA, 0x55,
+        ];
+
+        let (frames, skipped) = decoder.push(&stream);
+        let decoded: Vec<f64> = frames.iter().map(|frame| frame[0].1).collect();
+        assert_eq!(decoded, [1.0, 2.0]);
+        assert_eq!(skipped, 4);
+    }
+
+    #[test]
+    fn checksum_rejects_false_sync() {
+        let checksum = Trailer {
+            kind: ChecksumKind::Crc16Modbus,
+            byte_order: ByteOrder::LittleEndian,
+            skip_start: 2,
+            skip_end: 0,
+        };
+        let mut decoder = decoder(
+            vec![field("a", FieldType::U16, ByteOrder::BigEndian)],
+            checksum,
+        );
+        let frame = |value: u16| {
+            let mut frame = vec![0xAA, 0x55];
+            frame.extend_from_slice(&value.to_be_bytes());
+            checksum.append(&mut frame).unwrap();
+            frame
+        };
+
+        // The tail of a frame whose value starts with AA 55
+        let mut stream = frame(0xAA55)[2..].to_vec();
+        stream.extend(frame(0x0102));
+        let (frames, skipped) = decoder.push(&stream);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0][0].1, 0x0102 as f64);
+        assert_eq!(skipped, 4);
+
+        let mut corrupt = frame(7);
+        corrupt[3] ^= 1;
+        let (frames, _) = decoder.push(&corrupt);
+        assert!(frames.is_empty());
+    }
+
+    #[test]
+    fn held_frame_is_flushed() {
+        let mut decoder = decoder(
+            vec![field("a", FieldType::U8, ByteOrder::LittleEndian)],
+            Trailer::default(),
+        );
+        assert!(decoder.push(&[0xAA, 0x55]).0.is_empty());
+        assert!(!decoder.is_holding());
+        assert_eq!(decoder.flush(), None);
+
+        assert!(decoder.push(&[7]).0.is_empty());
+        assert!(decoder.is_holding());
+        assert_eq!(decoder.flush(), Some(vec![("a".to_string(), 7.0)]));
+        assert!(!decoder.is_holding());
+    }
+}
@@ -0,0 +1,225 @@
+use super::{ChartData, ChartXAxis};
+use egui::Color32;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufWriter, Result, Write};
+
+const SVG_WIDTH: f64 = 900.0;
+const SVG_HEIGHT: f64 = 450.0;
+const SVG_MARGIN_LEFT: f64 = 70.0;
+const SVG_MARGIN_RIGHT: f64 = 20.0;
+const SVG_MARGIN_TOP: f64 = 40.0;
+const SVG_MARGIN_BOTTOM: f64 = 40.0;
+
+/// A visible series as it is drawn: one polyline per run without gaps.
+pub struct SeriesSnapshot {
+    pub name: String,
+    pub color: Color32,
+    pub segments: Vec<Vec<(f64, f64)>>,
+}
+
+/// Everything needed to redraw the plot outside of egui.
+pub struct PlotSnapshot {
+    pub x_axis: ChartXAxis,
+    pub x_range: (f64, f64),
+    pub y_range: (f64, f64),
+    pub series: Vec<SeriesSnapshot>,
+}
+
+/// Write every buffered record to a CSV file: sample index, receive time in
+/// seconds and one column per series. Gaps are left empty.
+pub fn export_csv(path: &str, data: &ChartData) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    write!(writer, "sample,time_s")?;
+    for series in &data.series {
+        write!(writer, ",{}", csv_field(&series.name))?;
+    }
+    writeln!(writer)?;
+
+    for index in 0..data.len() {
+        write!(
+            writer,
+            "{},{:.6}",
+            data.first_index() + index as u64,
+            data.times[index]
+        )?;
+        for series in &data.series {
+            let value = series.values[index];
+            if value.is_finite() {
+                write!(writer, ",{value}")?;
+            } else {
+                write!(writer, ",")?;
+            }
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Save an RGBA image, e.g. a screenshot of the plot, as PNG.
+pub fn export_png(path: &str, image: &egui::ColorImage) -> Result<()> {
+    let [width, height] = image.size;
+    image::save_buffer(
+        path,
+        image.as_raw(),
+        width as u32,
+        height as u32,
+        image::ColorType::Rgba8,
+    )
+    .map_err(std::io::Error::other)
+}
+
+/// Draw the plot as an SVG file with grid, axis labels and a legend.
+pub fn export_svg(path: &str, plot: &PlotSnapshot) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(render_svg(plot).as_bytes())?;
+    writer.flush()
+}
+
+/// Pad an empty range, e.g. a flat signal or a single sample, the way the
+/// live plot does, so points can be placed in it.
+fn widen((min, max): (f64, f64)) -> (f64, f64) {
+    if max > min {
+        (min, max)
+    } else {
+        (min - 1.0, max + 1.0)
+    }
+}
+
+fn render_svg(plot: &PlotSnapshot) -> String {
+    let (x_min, x_max) = widen(plot.x_range);
+    let (y_min, y_max) = widen(plot.y_range);
+    let plot_width = SVG_WIDTH - SVG_MARGIN_LEFT - SVG_MARGIN_RIGHT;
+    let plot_height = SVG_HEIGHT - SVG_MARGIN_TOP - SVG_MARGIN_BOTTOM;
+    let to_x = |x: f64| SVG_MARGIN_LEFT + (x - x_min) / (x_max - x_min) * plot_width;
+    let to_y = |y: f64| SVG_MARGIN_TOP + (y_max - y) / (y_max - y_min) * plot_height;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{SVG_WIDTH}" height="{SVG_HEIGHT}" font-family="sans-serif" font-size="12">"#
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="100%" height="100%" fill="white"/><rect x="{SVG_MARGIN_LEFT}" y="{SVG_MARGIN_TOP}" width="{plot_width}" height="{plot_height}" fill="none" stroke="black"/>"#
+    );
+
+    // Grid and axis labels
+    for i in 0..=5 {
+        let y = y_min + (y_max - y_min) * i as f64 / 5.0;
+        let py = to_y(y);
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{SVG_MARGIN_LEFT}" y1="{py:.1}" x2="{:.1}" y2="{py:.1}" stroke="#ddd"/><text x="{:.1}" y="{:.1}" text-anchor="end">{y:.1}</text>"##,
+            SVG_MARGIN_LEFT + plot_width,
+            SVG_MARGIN_LEFT - 6.0,
+            py + 4.0
+        );
+    }
+    for i in 0..=6 {
+        let x = x_min + (x_max - x_min) * i as f64 / 6.0;
+        let px = to_x(x);
+        let label = match plot.x_axis {
+            ChartXAxis::Samples => format!("{x:.0}"),
+            ChartXAxis::Time => format!("{x:.1} s"),
+        };
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{px:.1}" y1="{SVG_MARGIN_TOP}" x2="{px:.1}" y2="{:.1}" stroke="#ddd"/><text x="{px:.1}" y="{:.1}" text-anchor="middle">{label}</text>"##,
+            SVG_MARGIN_TOP + plot_height,
+            SVG_HEIGHT - SVG_MARGIN_BOTTOM + 18.0
+        );
+    }
+
+    // Series, clipped to the plot area
+    let _ = writeln!(
+        svg,
+        r#"<clipPath id="plot"><rect x="{SVG_MARGIN_LEFT}" y="{SVG_MARGIN_TOP}" width="{plot_width}" height="{plot_height}"/></clipPath><g clip-path="url(#plot)" fill="none" stroke-width="1.5">"#
+    );
+    for series in &plot.series {
+        let color = svg_color(series.color);
+        for segment in &series.segments {
+            let points = segment
+                .iter()
+                .map(|&(x, y)| format!("{:.2},{:.2}", to_x(x), to_y(y)))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let _ = writeln!(svg, r#"<polyline stroke="{color}" points="{points}"/>"#);
+        }
+    }
+    let _ = writeln!(svg, "</g>");
+
+    // Legend above the plot
+    let mut legend_x = SVG_MARGIN_LEFT;
+    for series in &plot.series {
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{legend_x:.1}" y="14" width="12" height="12" fill="{}"/><text x="{:.1}" y="24">{}</text>"#,
+            svg_color(series.color),
+            legend_x + 16.0,
+            xml_escape(&series.name)
+        );
+        legend_x += 28.0 + 7.0 * series.name.chars().count() as f64;
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn svg_color(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(points: Vec<(f64, f64)>) -> PlotSnapshot {
+        let (x, y) = points[0];
+        PlotSnapshot {
+            x_axis: ChartXAxis::Samples,
+            x_range: (x, x),
+            y_range: (y, y),
+            series: vec![SeriesSnapshot {
+                name: "Series 1".to_string(),
+                color: Color32::RED,
+                segments: vec![points],
+            }],
+        }
+    }
+
+    #[test]
+    fn single_sample_is_drawn_inside_the_plot() {
+        let svg = render_svg(&snapshot(vec![(5.0, 3.0)]));
+        assert!(!svg.contains("NaN"));
+        assert!(!svg.contains("inf"));
+    }
+
+    #[test]
+    fn flat_signal_is_drawn_inside_the_plot() {
+        let mut plot = snapshot(vec![(0.0, 2.0), (1.0, 2.0), (2.0, 2.0)]);
+        plot.x_range = (0.0, 2.0);
+        let svg = render_svg(&plot);
+        assert!(!svg.contains("NaN"));
+        assert!(svg.contains(&format!(
+            "{:.2}",
+            SVG_HEIGHT / 2.0 + (SVG_MARGIN_TOP - SVG_MARGIN_BOTTOM) / 2.0
+        )));
+    }
+}
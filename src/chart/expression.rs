@@ -0,0 +1,547 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Longest moving average window accepted by `avg`.
+const MAX_AVERAGE_WINDOW: usize = 100_000;
+
+/// Deepest nesting of parentheses, signs and powers the parser accepts, so a
+/// long run of `((((` cannot overflow the stack.
+const MAX_DEPTH: usize = 100;
+
+/// A series computed from other series, e.g. `mag = sqrt(ax^2+ay^2+az^2)`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DerivedSeries {
+    pub name: String,
+    pub expression: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Power,
+}
+
+impl Operator {
+    fn apply(&self, a: f64, b: f64) -> f64 {
+        match self {
+            Operator::Add => a + b,
+            Operator::Subtract => a - b,
+            Operator::Multiply => a * b,
+            Operator::Divide => a / b,
+            Operator::Remainder => a % b,
+            Operator::Power => a.powf(b),
+        }
+    }
+}
+
+type Function = fn(&[f64]) -> f64;
+
+/// Stateless functions: name, number of arguments and implementation.
+fn function(name: &str) -> Option<(usize, Function)> {
+    let function: (usize, Function) = match name {
+        "sqrt" => (1, |a| a[0].sqrt()),
+        "abs" => (1, |a| a[0].abs()),
+        "sin" => (1, |a| a[0].sin()),
+        "cos" => (1, |a| a[0].cos()),
+        "tan" => (1, |a| a[0].tan()),
+        "asin" => (1, |a| a[0].asin()),
+        "acos" => (1, |a| a[0].acos()),
+        "atan" => (1, |a| a[0].atan()),
+        "atan2" => (2, |a| a[0].atan2(a[1])),
+        "exp" => (1, |a| a[0].exp()),
+        "ln" => (1, |a| a[0].ln()),
+        "log10" => (1, |a| a[0].log10()),
+        "floor" => (1, |a| a[0].floor()),
+        "ceil" => (1, |a| a[0].ceil()),
+        "round" => (1, |a| a[0].round()),
+        "min" => (2, |a| a[0].min(a[1])),
+        "max" => (2, |a| a[0].max(a[1])),
+        _ => return None,
+    };
+    Some(function)
+}
+
+enum Node {
+    Number(f64),
+    Series(String),
+    Negate(Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+    /// `avg(x, n)`: mean of the last `n` values.
+    Average {
+        argument: Box<Node>,
+        window: usize,
+        history: VecDeque<f64>,
+        sum: f64,
+    },
+    /// `diff(x)`: change since the previous value.
+    Difference {
+        argument: Box<Node>,
+        previous: Option<f64>,
+    },
+    /// `deriv(x)`: change per second since the previous value.
+    Derivative {
+        argument: Box<Node>,
+        previous: Option<(f64, f64)>,
+        /// Last nonzero time between values. Records received in one chunk
+        /// share a timestamp and reuse it.
+        interval: Option<f64>,
+    },
+}
+
+impl Node {
+    fn eval(&mut self, lookup: &dyn Fn(&str) -> Option<f64>, time: f64) -> f64 {
+        match self {
+            Node::Number(value) => *value,
+            Node::Series(name) => lookup(name).unwrap_or(f64::NAN),
+            Node::Negate(node) => -node.eval(lookup, time),
+            Node::Binary(operator, a, b) => {
+                let a = a.eval(lookup, time);
+                let b = b.eval(lookup, time);
+                operator.apply(a, b)
+            }
+            Node::Call(function, arguments) => {
+                let values: Vec<f64> = arguments
+                    .iter_mut()
+                    .map(|argument| argument.eval(lookup, time))
+                    .collect();
+                function(&values)
+            }
+            Node::Average {
+                argument,
+                window,
+                history,
+                sum,
+            } => {
+                let value = argument.eval(lookup, time);
+                if !value.is_finite() {
+                    return f64::NAN;
+                }
+                history.push_back(value);
+                *sum += value;
+                if history.len() > *window {
+                    *sum -= history.pop_front().unwrap_or_default();
+                }
+                *sum / history.len() as f64
+            }
+            Node::Difference { argument, previous } => {
+                let value = argument.eval(lookup, time);
+                if !value.is_finite() {
+                    return f64::NAN;
+                }
+                previous
+                    .replace(value)
+                    .map_or(f64::NAN, |previous| value - previous)
+            }
+            Node::Derivative {
+                argument,
+                previous,
+                interval,
+            } => {
+                let value = argument.eval(lookup, time);
+                if !value.is_finite() {
+                    return f64::NAN;
+                }
+                let Some((previous_time, previous_value)) = previous.replace((time, value)) else {
+                    return f64::NAN;
+                };
+                if time > previous_time {
+                    *interval = Some(time - previous_time);
+                }
+                interval.map_or(f64::NAN, |dt| (value - previous_value) / dt)
+            }
+        }
+    }
+}
+
+/// A compiled expression over series values. Series are referenced by
+/// name, or as `[Series 1]` when the name is not a plain identifier.
+pub struct Expression {
+    root: Node,
+}
+
+impl Expression {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            chars: text.chars().collect(),
+            position: 0,
+            depth: 0,
+        };
+        let root = parser.expression()?;
+        parser.skip_whitespace();
+        if let Some(c) = parser.peek() {
+            return Err(format!(
+                "Unexpected '{c}' at position {}",
+                parser.position + 1
+            ));
+        }
+        Ok(Self { root })
+    }
+
+    /// Compute the value for one record. `lookup` returns the value of a
+    /// series in that record; a missing value makes the result NaN.
+    pub fn eval(&mut self, lookup: &dyn Fn(&str) -> Option<f64>, time: f64) -> f64 {
+        self.root.eval(lookup, time)
+    }
+}
+
+/// Recursive descent parser. Precedence from low to high: `+ -`, `* / %`,
+/// unary minus, `^` (right associative).
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    /// Nesting level of the part being parsed.
+    depth: usize,
+}
+
+impl Parser {
+    /// Parse a nested part with `parse`, failing when it is nested too deeply.
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Node, String>) -> Result<Node, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(format!(
+                "Expression nested too deeply at position {}",
+                self.position + 1
+            ));
+        }
+        self.depth += 1;
+        let node = parse(self);
+        self.depth -= 1;
+        node
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    /// Consume `c` if it is the next non-blank character.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(format!("Expected '{c}' at position {}", self.position + 1))
+        }
+    }
+
+    fn expression(&mut self) -> Result<Node, String> {
+        let mut node = self.term()?;
+        loop {
+            let operator = if self.eat('+') {
+                Operator::Add
+            } else if self.eat('-') {
+                Operator::Subtract
+            } else {
+                return Ok(node);
+            };
+            node = Node::Binary(operator, Box::new(node), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        loop {
+            let operator = if self.eat('*') {
+                Operator::Multiply
+            } else if self.eat('/') {
+                Operator::Divide
+            } else if self.eat('%') {
+                Operator::Remainder
+            } else {
+                return Ok(node);
+            };
+            node = Node::Binary(operator, Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.eat('-') {
+            Ok(Node::Negate(Box::new(self.nested(Self::unary)?)))
+        } else if self.eat('+') {
+            self.nested(Self::unary)
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<Node, String> {
+        let base = self.atom()?;
+        if self.eat('^') {
+            Ok(Node::Binary(
+                Operator::Power,
+                Box::new(base),
+                Box::new(self.nested(Self::unary)?),
+            ))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        self.skip_whitespace();
+        let start = self.position;
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let node = self.nested(Self::expression)?;
+                self.expect(')')?;
+                Ok(node)
+            }
+            Some('[') => {
+                self.position += 1;
+                let name: String = self.take_while(|c| c != ']');
+                self.expect(']')?;
+                Ok(Node::Series(name.trim().to_string()))
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let name = self.take_while(|c| c.is_alphanumeric() || c == '_' || c == '.');
+                if self.eat('(') {
+                    self.call(&name, start)
+                } else if name == "pi" {
+                    Ok(Node::Number(std::f64::consts::PI))
+                } else {
+                    Ok(Node::Series(name))
+                }
+            }
+            Some(c) => Err(format!("Unexpected '{c}' at position {}", start + 1)),
+            None => Err("Unexpected end of expression".to_string()),
+        }
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let start = self.position;
+        while self.peek().is_some_and(&predicate) {
+            self.position += 1;
+        }
+        self.chars[start..self.position].iter().collect()
+    }
+
+    fn number(&mut self) -> Result<Node, String> {
+        let start = self.position;
+        let mut text = self.take_while(|c| c.is_ascii_digit() || c == '.');
+        // Exponent, e.g. 1.5e-3
+        if matches!(self.peek(), Some('e' | 'E')) {
+            let next = self.chars.get(self.position + 1).copied();
+            let after_sign = self.chars.get(self.position + 2).copied();
+            let has_exponent = next.is_some_and(|c| c.is_ascii_digit())
+                || (matches!(next, Some('+' | '-'))
+                    && after_sign.is_some_and(|c| c.is_ascii_digit()));
+            if has_exponent {
+                text.push('e');
+                self.position += 1;
+                if let Some(sign @ ('+' | '-')) = self.peek() {
+                    text.push(sign);
+                    self.position += 1;
+                }
+                text.push_str(&self.take_while(|c| c.is_ascii_digit()));
+            }
+        }
+        text.parse()
+            .map(Node::Number)
+            .map_err(|_| format!("Invalid number '{text}' at position {}", start + 1))
+    }
+
+    /// Arguments of a function call after its opening parenthesis.
+    fn call(&mut self, name: &str, start: usize) -> Result<Node, String> {
+        let mut arguments = Vec::new();
+        if !self.eat(')') {
+            loop {
+                arguments.push(self.nested(Self::expression)?);
+                if self.eat(')') {
+                    break;
+                }
+                self.expect(',')?;
+            }
+        }
+
+        let arity = |expected: usize| {
+            if arguments.len() == expected {
+                Ok(())
+            } else {
+                Err(format!(
+                    "{name}() takes {expected} argument{} at position {}",
+                    if expected == 1 { "" } else { "s" },
+                    start + 1
+                ))
+            }
+        };
+        match name {
+            "avg" => {
+                arity(2)?;
+                let Some(Node::Number(window)) = arguments.pop() else {
+                    return Err("avg(x, n) needs a number of samples n".to_string());
+                };
+                if window < 1.0 || window > MAX_AVERAGE_WINDOW as f64 {
+                    return Err(format!(
+                        "avg() window must be between 1 and {MAX_AVERAGE_WINDOW}"
+                    ));
+                }
+                Ok(Node::Average {
+                    argument: Box::new(arguments.remove(0)),
+                    window: window as usize,
+                    history: VecDeque::new(),
+                    sum: 0.0,
+                })
+            }
+            "diff" => {
+                arity(1)?;
+                Ok(Node::Difference {
+                    argument: Box::new(arguments.remove(0)),
+                    previous: None,
+                })
+            }
+            "deriv" => {
+                arity(1)?;
+                Ok(Node::Derivative {
+                    argument: Box::new(arguments.remove(0)),
+                    previous: None,
+                    interval: None,
+                })
+            }
+            _ => {
+                let Some((expected, function)) = function(name) else {
+                    return Err(format!("Unknown function '{name}'"));
+                };
+                arity(expected)?;
+                Ok(Node::Call(function, arguments))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(text: &str) -> f64 {
+        Expression::parse(text).unwrap().eval(&|_| None, 0.0)
+    }
+
+    /// Evaluate `text` once per value of the series `x`, at 0.1 s steps.
+    fn eval_series(text: &str, values: &[f64]) -> Vec<f64> {
+        let mut expression = Expression::parse(text).unwrap();
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| {
+                let lookup = move |name: &str| (name == "x").then_some(value);
+                expression.eval(&lookup, i as f64 * 0.1)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3"), 7.0);
+        assert_eq!(eval("(1 + 2) * 3"), 9.0);
+        assert_eq!(eval("-2^2"), -4.0);
+        assert_eq!(eval("2^3^2"), 512.0);
+        assert_eq!(eval("2^-1"), 0.5);
+        assert_eq!(eval("10 - 4 - 3"), 3.0);
+        assert_eq!(eval("7 % 4 * 2"), 6.0);
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(eval("1.5e-3"), 0.0015);
+        assert_eq!(eval("2E+2"), 200.0);
+        assert_eq!(eval(".5"), 0.5);
+        assert!(Expression::parse("1.2.3").is_err());
+    }
+
+    #[test]
+    fn series_names() {
+        let lookup = |name: &str| match name {
+            "Series 1" => Some(3.0),
+            "ax" => Some(4.0),
+            _ => None,
+        };
+        let mut expression = Expression::parse("sqrt([Series 1]^2 + ax^2)").unwrap();
+        assert_eq!(expression.eval(&lookup, 0.0), 5.0);
+
+        let mut missing = Expression::parse("[Series 2] + 1").unwrap();
+        assert!(missing.eval(&lookup, 0.0).is_nan());
+    }
+
+    #[test]
+    fn moving_average() {
+        let averages = eval_series("avg(x, 2)", &[1.0, 3.0, 5.0, f64::NAN, 9.0]);
+        assert_eq!(averages[..3], [1.0, 2.0, 4.0]);
+        assert!(averages[3].is_nan());
+        assert_eq!(averages[4], 7.0);
+
+        assert!(Expression::parse("avg(x, 0)").is_err());
+        assert!(Expression::parse("avg(x, x)").is_err());
+    }
+
+    #[test]
+    fn difference_and_derivative() {
+        let differences = eval_series("diff(x)", &[1.0, 4.0, 2.0]);
+        assert!(differences[0].is_nan());
+        assert_eq!(differences[1..], [3.0, -2.0]);
+
+        let derivatives = eval_series("deriv(x)", &[0.0, 1.0, 3.0]);
+        assert!(derivatives[0].is_nan());
+        assert!((derivatives[1] - 10.0).abs() < 1e-9);
+        assert!((derivatives[2] - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn derivative_reuses_interval_within_a_chunk() {
+        let mut expression = Expression::parse("deriv(x)").unwrap();
+        let mut at = |value: f64, time: f64| expression.eval(&|_| Some(value), time);
+        assert!(at(0.0, 0.0).is_nan());
+        assert_eq!(at(2.0, 0.5), 4.0);
+        // Same timestamp: the last interval is used
+        assert_eq!(at(3.0, 0.5), 2.0);
+    }
+
+    #[test]
+    fn arity_errors() {
+        assert!(Expression::parse("sqrt(1, 2)").is_err());
+        assert!(Expression::parse("atan2(1)").is_err());
+        assert!(Expression::parse("diff()").is_err());
+        assert!(Expression::parse("avg(x)").is_err());
+        assert!(Expression::parse("nope(1)").is_err());
+        assert_eq!(eval("max(2, 5)"), 5.0);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert!(Expression::parse("").is_err());
+        assert!(Expression::parse("1 +").is_err());
+        assert!(Expression::parse("(1").is_err());
+        assert!(Expression::parse("1 )").is_err());
+        assert!(Expression::parse("[x").is_err());
+    }
+
+    #[test]
+    fn nesting_depth_is_limited() {
+        assert_eq!(eval(&format!("{}1{}", "(".repeat(50), ")".repeat(50))), 1.0);
+
+        let deep = format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert!(Expression::parse(&deep).is_err());
+        assert!(Expression::parse(&"-".repeat(100_000)).is_err());
+        assert!(Expression::parse(&"2^".repeat(100_000)).is_err());
+    }
+}
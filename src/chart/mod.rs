@@ -0,0 +1,306 @@
+pub mod alarm;
+pub mod binary;
+pub mod export;
+pub mod expression;
+pub mod parser;
+pub mod spectrum;
+pub mod stats;
+pub mod trigger;
+
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Upper bound on the records kept, whatever the other limits say.
+pub const MAX_RECORDS: usize = 1_000_000;
+
+/// What the chart's X axis shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ChartXAxis {
+    /// Record number since the chart was cleared.
+    #[default]
+    Samples,
+    /// Seconds since the chart was cleared, from the receive timestamps.
+    Time,
+}
+
+impl ChartXAxis {
+    pub const ALL: [ChartXAxis; 2] = [ChartXAxis::Samples, ChartXAxis::Time];
+}
+
+impl std::fmt::Display for ChartXAxis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChartXAxis::Samples => write!(f, "Samples"),
+            ChartXAxis::Time => write!(f, "Time"),
+        }
+    }
+}
+
+/// How the chart presents its series.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ChartDisplay {
+    /// Values over samples or time.
+    #[default]
+    Time,
+    /// Magnitude over frequency of the latest samples of one series.
+    Spectrum,
+    /// Oscilloscope-style captures around a level crossing of one series.
+    Triggered,
+    /// One series against another.
+    Xy,
+    /// Distribution of the buffered values of one series.
+    Histogram,
+}
+
+impl ChartDisplay {
+    pub const ALL: [ChartDisplay; 5] = [
+        ChartDisplay::Time,
+        ChartDisplay::Spectrum,
+        ChartDisplay::Triggered,
+        ChartDisplay::Xy,
+        ChartDisplay::Histogram,
+    ];
+}
+
+impl std::fmt::Display for ChartDisplay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChartDisplay::Time => write!(f, "Time plot"),
+            ChartDisplay::Spectrum => write!(f, "Spectrum (FFT)"),
+            ChartDisplay::Triggered => write!(f, "Triggered (scope)"),
+            ChartDisplay::Xy => write!(f, "XY plot"),
+            ChartDisplay::Histogram => write!(f, "Histogram"),
+        }
+    }
+}
+
+/// How the series of the time plot share the plot area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ChartLayout {
+    /// One plot, with a left and an optional right Y axis.
+    #[default]
+    Overlay,
+    /// One plot per series, stacked and sharing the X axis.
+    Stacked,
+}
+
+impl ChartLayout {
+    pub const ALL: [ChartLayout; 2] = [ChartLayout::Overlay, ChartLayout::Stacked];
+}
+
+impl std::fmt::Display for ChartLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChartLayout::Overlay => write!(f, "Overlay"),
+            ChartLayout::Stacked => write!(f, "Stacked"),
+        }
+    }
+}
+
+/// Y axis a series is scaled to in the overlay layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum YAxis {
+    #[default]
+    Left,
+    Right,
+}
+
+/// Colors given to new series, in order.
+pub const PALETTE: [Color32; 8] = [
+    Color32::from_rgb(0, 150, 255),
+    Color32::from_rgb(255, 90, 70),
+    Color32::from_rgb(80, 200, 80),
+    Color32::from_rgb(240, 190, 0),
+    Color32::from_rgb(190, 100, 255),
+    Color32::from_rgb(0, 200, 200),
+    Color32::from_rgb(255, 130, 200),
+    Color32::from_rgb(160, 160, 160),
+];
+
+/// One plotted channel. `values` is aligned with the records of
+/// [`ChartData`]; records that carried no value for this series hold NaN.
+pub struct Series {
+    pub name: String,
+    pub color: Color32,
+    pub visible: bool,
+    pub values: VecDeque<f64>,
+    /// Computed from other series instead of received.
+    pub derived: bool,
+    pub axis: YAxis,
+}
+
+impl Series {
+    fn new(name: String, index: usize, len: usize) -> Self {
+        Self {
+            name,
+            color: PALETTE[index % PALETTE.len()],
+            visible: true,
+            values: VecDeque::from(vec![f64::NAN; len]),
+            derived: false,
+            axis: YAxis::Left,
+        }
+    }
+
+    /// Most recent value that is not a gap.
+    pub fn latest(&self) -> Option<f64> {
+        self.values.iter().rev().copied().find(|v| v.is_finite())
+    }
+}
+
+/// Buffer of received records, one column per series.
+pub struct ChartData {
+    pub series: Vec<Series>,
+    /// Receive time of each record, in seconds.
+    pub times: VecDeque<f64>,
+    /// Number of records kept before the oldest are dropped.
+    pub capacity: usize,
+    /// Records older than this many seconds are dropped.
+    pub max_age: Option<f64>,
+    /// Sample index from which records are kept regardless of `capacity`
+    /// and `max_age`, e.g. while a paused view shows them.
+    pub keep_from: Option<u64>,
+    /// Sample index of the oldest record still in the buffer.
+    first_index: u64,
+    len: usize,
+}
+
+impl ChartData {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            series: Vec::new(),
+            times: VecDeque::new(),
+            capacity,
+            max_age: None,
+            keep_from: None,
+            first_index: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn first_index(&self) -> u64 {
+        self.first_index
+    }
+
+    /// Change the buffer limits, dropping records that no longer fit.
+    pub fn set_limits(&mut self, capacity: usize, max_age: Option<f64>, keep_from: Option<u64>) {
+        self.capacity = capacity;
+        self.max_age = max_age;
+        self.keep_from = keep_from;
+        self.trim();
+    }
+
+    /// Number of received series. They come first; derived series follow.
+    fn received_count(&self) -> usize {
+        self.series.iter().filter(|series| !series.derived).count()
+    }
+
+    /// Append a record whose values are matched to series by position.
+    pub fn push_record(&mut self, values: &[f64], time: f64) {
+        for index in self.received_count()..values.len() {
+            self.series.insert(
+                index,
+                Series::new(format!("Series {}", index + 1), index, self.len),
+            );
+        }
+
+        for (index, series) in self.series.iter_mut().enumerate() {
+            let value = if series.derived {
+                f64::NAN
+            } else {
+                values.get(index).copied().unwrap_or(f64::NAN)
+            };
+            series.values.push_back(value);
+        }
+        self.times.push_back(time);
+        self.len += 1;
+        self.trim();
+    }
+
+    /// Append a record of labeled values. Unknown names start a new series;
+    /// series missing from the record get a gap.
+    pub fn push_named(&mut self, values: &[(String, f64)], time: f64) {
+        for (name, _) in values {
+            if !self
+                .series
+                .iter()
+                .any(|series| !series.derived && &series.name == name)
+            {
+                let index = self.received_count();
+                self.series
+                    .insert(index, Series::new(name.clone(), index, self.len));
+            }
+        }
+
+        for series in &mut self.series {
+            let value = values
+                .iter()
+                .rev()
+                .find(|(name, _)| !series.derived && *name == series.name)
+                .map_or(f64::NAN, |(_, value)| *value);
+            series.values.push_back(value);
+        }
+        self.times.push_back(time);
+        self.len += 1;
+        self.trim();
+    }
+
+    /// Set the value of the derived series `name` in the record at `index`,
+    /// adding the series if it does not exist yet.
+    pub fn set_derived(&mut self, name: &str, index: usize, value: f64) {
+        let position = match self
+            .series
+            .iter()
+            .position(|series| series.derived && series.name == name)
+        {
+            Some(position) => position,
+            None => {
+                let mut series = Series::new(name.to_string(), self.series.len(), self.len);
+                series.derived = true;
+                self.series.push(series);
+                self.series.len() - 1
+            }
+        };
+        self.series[position].values[index] = value;
+    }
+
+    pub fn remove_derived(&mut self) {
+        self.series.retain(|series| !series.derived);
+    }
+
+    pub fn clear(&mut self) {
+        self.series.clear();
+        self.times.clear();
+        self.first_index = 0;
+        self.len = 0;
+    }
+
+    fn trim(&mut self) {
+        let oldest = match (self.max_age, self.times.back()) {
+            (Some(max_age), Some(&latest)) => latest - max_age,
+            _ => f64::NEG_INFINITY,
+        };
+        loop {
+            let kept = self.keep_from.is_some_and(|keep| self.first_index >= keep);
+            let expired =
+                self.len > self.capacity || self.times.front().is_some_and(|&t| t < oldest);
+            if !(self.len > MAX_RECORDS || (expired && !kept)) {
+                break;
+            }
+            self.times.pop_front();
+            for series in &mut self.series {
+                series.values.pop_front();
+            }
+            self.len -= 1;
+            self.first_index += 1;
+        }
+    }
+}
@@ -0,0 +1,426 @@
+use super::binary::{BinaryDecoder, BinaryFrameFormat};
+use crate::generalsettings::AppSettings;
+use crate::payload::parse_escaped;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Longest record kept while waiting for its delimiter. Anything longer is
+/// not chart data and is discarded.
+const MAX_RECORD_LENGTH: usize = 4096;
+
+/// How a received record is turned into series values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ChartParseMode {
+    /// Values matched to series by their column.
+    #[default]
+    Columns,
+    /// `name:value` or `name=value` pairs, one series per name.
+    Named,
+    /// Binary frames described by a [`BinaryFrameFormat`], one series per field.
+    Binary,
+    /// One series of values, each ended by any value or record delimiter,
+    /// e.g. `1,2,3,4,` without line breaks.
+    Stream,
+}
+
+impl ChartParseMode {
+    pub const ALL: [ChartParseMode; 4] = [
+        ChartParseMode::Columns,
+        ChartParseMode::Named,
+        ChartParseMode::Binary,
+        ChartParseMode::Stream,
+    ];
+}
+
+impl std::fmt::Display for ChartParseMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChartParseMode::Columns => write!(f, "Columns"),
+            ChartParseMode::Named => write!(f, "Named (name:value)"),
+            ChartParseMode::Binary => write!(f, "Binary frames"),
+            ChartParseMode::Stream => write!(f, "Stream (one series)"),
+        }
+    }
+}
+
+/// Values extracted from one record.
+pub enum Record {
+    Columns(Vec<f64>),
+    Named(Vec<(String, f64)>),
+}
+
+/// Reassembles received chunks into records ending with a delimiter.
+pub struct RecordSplitter {
+    buffer: Vec<u8>,
+    delimiter: Vec<u8>,
+}
+
+impl RecordSplitter {
+    pub fn new(delimiter: Vec<u8>) -> Self {
+        Self {
+            buffer: Vec::new(),
+            delimiter,
+        }
+    }
+
+    /// Add received bytes and return the records completed by them, without
+    /// the delimiter and surrounding whitespace. The second value is true if
+    /// an overlong partial record had to be discarded.
+    pub fn push(&mut self, data: &[u8]) -> (Vec<String>, bool) {
+        self.buffer.extend_from_slice(data);
+
+        let mut records = Vec::new();
+        while let Some(pos) = self
+            .buffer
+            .windows(self.delimiter.len())
+            .position(|window| window == self.delimiter)
+        {
+            let record: Vec<u8> = self.buffer.drain(..pos + self.delimiter.len()).collect();
+            records.push(String::from_utf8_lossy(&record[..pos]).trim().to_string());
+        }
+
+        let overflow = self.buffer.len() > MAX_RECORD_LENGTH;
+        if overflow {
+            self.buffer.clear();
+        }
+        (records, overflow)
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+/// The settings a [`ChartParser`] is built from.
+#[derive(Clone, PartialEq)]
+pub struct ParserConfig {
+    pub value_delimiters: String,
+    pub record_delimiter: String,
+    pub number_regex: String,
+    pub binary_format: BinaryFrameFormat,
+}
+
+impl ParserConfig {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            value_delimiters: settings.chart_value_delimiters.clone(),
+            record_delimiter: settings.chart_record_delimiter.clone(),
+            number_regex: settings.chart_number_regex.clone(),
+            binary_format: settings.chart_binary_format.clone(),
+        }
+    }
+}
+
+/// Turns the received byte stream into chart records, using the delimiters,
+/// number pattern and binary frame format from the settings.
+pub struct ChartParser {
+    splitter: RecordSplitter,
+    value_delimiters: Vec<char>,
+    /// Characters ending a value in stream mode: the value delimiters and
+    /// those of the record delimiter.
+    stream_separators: Vec<char>,
+    /// Partial value of stream mode waiting for its separator.
+    stream_pending: String,
+    number_regex: Option<Regex>,
+    binary: BinaryDecoder,
+    /// Records that were not chart data, such as log messages.
+    pub ignored: u64,
+    /// Bytes skipped while looking for the sync header of a binary frame.
+    pub skipped_bytes: u64,
+}
+
+impl ChartParser {
+    /// Build a parser from the chart settings. Delimiters accept the escapes
+    /// of [`parse_escaped`], e.g. `\t` or `\r\n`.
+    pub fn from_settings(settings: &AppSettings) -> Result<Self, String> {
+        Self::new(&ParserConfig::from_settings(settings))
+    }
+
+    pub fn new(settings: &ParserConfig) -> Result<Self, String> {
+        let record_delimiter = parse_escaped(&settings.record_delimiter)
+            .map_err(|e| format!("Record delimiter: {e}"))?;
+        if record_delimiter.is_empty() {
+            return Err("Record delimiter must not be empty".to_string());
+        }
+
+        let value_delimiters = String::from_utf8(
+            parse_escaped(&settings.value_delimiters)
+                .map_err(|e| format!("Value delimiters: {e}"))?,
+        )
+        .map_err(|_| "Value delimiters must be valid text".to_string())?
+        .chars()
+        .collect::<Vec<char>>();
+        if value_delimiters.is_empty() {
+            return Err("Value delimiters must not be empty".to_string());
+        }
+
+        let number_regex = match settings.number_regex.trim() {
+            "" => None,
+            pattern => Some(Regex::new(pattern).map_err(|e| format!("Number regex: {e}"))?),
+        };
+
+        let binary = BinaryDecoder::new(&settings.binary_format)?;
+
+        let mut stream_separators = value_delimiters.clone();
+        stream_separators.extend(String::from_utf8_lossy(&record_delimiter).chars());
+
+        Ok(Self {
+            splitter: RecordSplitter::new(record_delimiter),
+            value_delimiters,
+            stream_separators,
+            stream_pending: String::new(),
+            number_regex,
+            binary,
+            ignored: 0,
+            skipped_bytes: 0,
+        })
+    }
+
+    /// Add received bytes and return the records completed by them.
+    pub fn push(&mut self, data: &[u8], mode: ChartParseMode) -> Vec<Record> {
+        match mode {
+            ChartParseMode::Columns => self.push_text(data, false),
+            ChartParseMode::Named => self.push_text(data, true),
+            ChartParseMode::Binary => {
+                let (frames, skipped) = self.binary.push(data);
+                self.skipped_bytes += skipped as u64;
+                frames.into_iter().map(Record::Named).collect()
+            }
+            ChartParseMode::Stream => self.push_stream(data),
+        }
+    }
+
+    /// Whether records are held back until more data or a quiet line
+    /// confirms them; see [`ChartParser::flush`].
+    pub fn is_holding(&self, mode: ChartParseMode) -> bool {
+        mode == ChartParseMode::Binary && self.binary.is_holding()
+    }
+
+    /// Return the records held back for more data, once the line went quiet.
+    pub fn flush(&mut self, mode: ChartParseMode) -> Vec<Record> {
+        match mode {
+            ChartParseMode::Binary => self.binary.flush().map(Record::Named).into_iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Split a stream of single values, each a record of one column.
+    fn push_stream(&mut self, data: &[u8]) -> Vec<Record> {
+        self.stream_pending.push_str(&String::from_utf8_lossy(data));
+
+        let mut records = Vec::new();
+        while let Some(pos) = self.stream_pending.find(self.stream_separators.as_slice()) {
+            let separator_len = self.stream_pending[pos..]
+                .chars()
+                .next()
+                .map_or(1, char::len_utf8);
+            let field: String = self.stream_pending.drain(..pos + separator_len).collect();
+            let field = field[..pos].trim();
+            if field.is_empty() {
+                continue;
+            }
+            match field.parse::<f64>() {
+                Ok(value) => records.push(Record::Columns(vec![value])),
+                Err(_) => self.ignored += 1,
+            }
+        }
+
+        if self.stream_pending.len() > MAX_RECORD_LENGTH {
+            self.stream_pending.clear();
+            self.ignored += 1;
+        }
+        records
+    }
+
+    /// Split text into records and parse them as columns or named values.
+    fn push_text(&mut self, data: &[u8], named: bool) -> Vec<Record> {
+        let (texts, overflow) = self.splitter.push(data);
+        if overflow {
+            self.ignored += 1;
+        }
+
+        let mut records = Vec::new();
+        for text in texts.iter().filter(|text| !text.is_empty()) {
+            let record = if named {
+                Some(self.parse_named(text))
+                    .filter(|pairs| !pairs.is_empty())
+                    .map(Record::Named)
+            } else {
+                self.parse_columns(text).map(Record::Columns)
+            };
+            match record {
+                Some(record) => records.push(record),
+                None => self.ignored += 1,
+            }
+        }
+        records
+    }
+
+    pub fn clear(&mut self) {
+        self.splitter.clear();
+        self.stream_pending.clear();
+        self.binary.clear();
+        self.ignored = 0;
+        self.skipped_bytes = 0;
+    }
+
+    /// Split a record into numeric columns. A record with any field that is
+    /// not a number is taken for a log message and ignored. With a number
+    /// regex, the values are its matches (or its first capture group) and
+    /// the rest of the text is ignored.
+    fn parse_columns(&self, text: &str) -> Option<Vec<f64>> {
+        let values: Vec<f64> = match &self.number_regex {
+            Some(regex) => regex
+                .captures_iter(text)
+                .filter_map(|captures| {
+                    captures
+                        .get(1)
+                        .or_else(|| captures.get(0))
+                        .and_then(|found| found.as_str().parse().ok())
+                })
+                .collect(),
+            None => self.split_columns(text)?,
+        };
+
+        values.iter().any(|value| !value.is_nan()).then_some(values)
+    }
+
+    /// Values separated by the value delimiters. Runs of blank delimiters
+    /// count as one, while an empty field between other delimiters, as in
+    /// `1,,3`, is a gap that keeps the later columns in place.
+    fn split_columns(&self, text: &str) -> Option<Vec<f64>> {
+        let (blank, marks): (Vec<char>, Vec<char>) = self
+            .value_delimiters
+            .iter()
+            .partition(|c| c.is_whitespace());
+        let mut fields: Vec<&str> = text.split(marks.as_slice()).collect();
+        // A delimiter ending the record does not start another column
+        if fields.len() > 1 && fields.last().is_some_and(|field| field.trim().is_empty()) {
+            fields.pop();
+        }
+
+        let mut values = Vec::new();
+        for field in fields {
+            let start = values.len();
+            for token in field
+                .split(blank.as_slice())
+                .filter(|token| !token.trim().is_empty())
+            {
+                values.push(token.trim().parse::<f64>().ok()?);
+            }
+            if values.len() == start {
+                values.push(f64::NAN);
+            }
+        }
+        Some(values)
+    }
+
+    /// Extract `name:value` / `name=value` pairs separated by the value
+    /// delimiters, e.g. `temp:23.5,hum=40` or `temp: 23.5 hum: 40`. Fields
+    /// without a name or with a non-numeric value are skipped.
+    fn parse_named(&self, text: &str) -> Vec<(String, f64)> {
+        let mut pairs = Vec::new();
+        let mut pending_name: Option<&str> = None;
+
+        for field in text
+            .split(self.value_delimiters.as_slice())
+            .filter(|field| !field.is_empty())
+        {
+            // A name whose value followed a delimiter: "temp: 23.5"
+            if let Some(name) = pending_name.take() {
+                if let Ok(value) = field.trim().parse::<f64>() {
+                    pairs.push((name.to_string(), value));
+                    continue;
+                }
+            }
+
+            let Some((name, value)) = field.split_once([':', '=']) else {
+                continue;
+            };
+            let name = name.trim();
+            let value = value.trim();
+            if name.is_empty() {
+                continue;
+            }
+            if value.is_empty() {
+                pending_name = Some(name);
+            } else if let Ok(value) = value.parse::<f64>() {
+                pairs.push((name.to_string(), value));
+            }
+        }
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parser() -> ChartParser {
+        ChartParser::from_settings(&AppSettings::default()).unwrap()
+    }
+
+    fn columns(records: Vec<Record>) -> Vec<Vec<f64>> {
+        records
+            .into_iter()
+            .map(|record| match record {
+                Record::Columns(values) => values,
+                Record::Named(_) => panic!("expected columns"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stream_of_single_values() {
+        let mut parser = parser();
+        let records = parser.push(b"1,2,3.5,-4,", ChartParseMode::Stream);
+        assert_eq!(columns(records), [[1.0], [2.0], [3.5], [-4.0]]);
+    }
+
+    #[test]
+    fn stream_value_split_across_chunks() {
+        let mut parser = parser();
+        assert_eq!(
+            columns(parser.push(b"10,2", ChartParseMode::Stream)),
+            [[10.0]]
+        );
+        assert_eq!(
+            columns(parser.push(b"5,3\n", ChartParseMode::Stream)),
+            [[25.0], [3.0]]
+        );
+    }
+
+    #[test]
+    fn stream_skips_text() {
+        let mut parser = parser();
+        let records = parser.push(b"1,boot,2,", ChartParseMode::Stream);
+        assert_eq!(columns(records), [[1.0], [2.0]]);
+        assert_eq!(parser.ignored, 1);
+    }
+
+    #[test]
+    fn columns_need_record_delimiter() {
+        let mut parser = parser();
+        assert!(parser.push(b"1,2,3", ChartParseMode::Columns).is_empty());
+        let records = parser.push(b"\n4 5 6\n", ChartParseMode::Columns);
+        assert_eq!(columns(records), [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+    }
+
+    #[test]
+    fn empty_column_is_a_gap() {
+        let mut parser = parser();
+        let columns = columns(parser.push(b"1,,3\n4, 5,\n6\t 7\n", ChartParseMode::Columns));
+        assert_eq!(columns.len(), 3);
+        assert_eq!(columns[0][0], 1.0);
+        assert!(columns[0][1].is_nan());
+        assert_eq!(columns[0][2], 3.0);
+        assert_eq!(columns[1], [4.0, 5.0]);
+        assert_eq!(columns[2], [6.0, 7.0]);
+    }
+
+    #[test]
+    fn blank_record_is_skipped() {
+        let mut parser = parser();
+        assert!(parser.push(b"\n , \n", ChartParseMode::Columns).is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// FFT lengths offered for the spectrum view.
+pub const FFT_SIZES: [usize; 9] = [64, 128, 256, 512, 1024, 2048, 4096, 8192, 16384];
+
+/// Magnitudes below this are shown at its level instead of minus infinity.
+const MIN_MAGNITUDE: f64 = 1e-10;
+
+/// Window applied to the samples before the FFT, trading frequency
+/// resolution for less leakage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WindowFunction {
+    Rectangular,
+    #[default]
+    Hann,
+    Hamming,
+}
+
+impl WindowFunction {
+    pub const ALL: [WindowFunction; 3] = [
+        WindowFunction::Rectangular,
+        WindowFunction::Hann,
+        WindowFunction::Hamming,
+    ];
+
+    /// Weight of sample `index` in a window of `len` samples.
+    fn weight(&self, index: usize, len: usize) -> f64 {
+        if len < 2 {
+            return 1.0;
+        }
+        let phase = 2.0 * PI * index as f64 / (len - 1) as f64;
+        match self {
+            WindowFunction::Rectangular => 1.0,
+            WindowFunction::Hann => 0.5 - 0.5 * phase.cos(),
+            WindowFunction::Hamming => 0.54 - 0.46 * phase.cos(),
+        }
+    }
+}
+
+impl std::fmt::Display for WindowFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WindowFunction::Rectangular => write!(f, "Rectangular"),
+            WindowFunction::Hann => write!(f, "Hann"),
+            WindowFunction::Hamming => write!(f, "Hamming"),
+        }
+    }
+}
+
+/// Single-sided amplitude spectrum of `samples` as `(frequency, dB)` pairs,
+/// from DC to the Nyquist frequency. The length of `samples` must be a power
+/// of two of at least 2, otherwise the spectrum is empty. With the sample rate
+/// in Hz the frequencies are in Hz; with a rate of 1 they are in cycles per
+/// sample.
+pub fn spectrum(samples: &[f64], window: WindowFunction, sample_rate: f64) -> Vec<(f64, f64)> {
+    let len = samples.len();
+    if len < 2 || !len.is_power_of_two() {
+        return Vec::new();
+    }
+
+    let weights: Vec<f64> = (0..len).map(|i| window.weight(i, len)).collect();
+    let mut re: Vec<f64> = samples.iter().zip(&weights).map(|(s, w)| s * w).collect();
+    let mut im = vec![0.0; len];
+    fft(&mut re, &mut im);
+
+    // Scale so a full-scale sine reads its amplitude regardless of window
+    let gain: f64 = weights.iter().sum();
+    (0..=len / 2)
+        .map(|bin| {
+            let scale = if bin == 0 || bin == len / 2 { 1.0 } else { 2.0 };
+            let magnitude = (re[bin].hypot(im[bin]) * scale / gain).max(MIN_MAGNITUDE);
+            (
+                bin as f64 * sample_rate / len as f64,
+                20.0 * magnitude.log10(),
+            )
+        })
+        .collect()
+}
+
+/// In-place iterative radix-2 FFT. Both slices must have the same power of
+/// two length.
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let len = re.len();
+
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..len {
+        let mut bit = len >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut size = 2;
+    while size <= len {
+        let angle = -2.0 * PI / size as f64;
+        for start in (0..len).step_by(size) {
+            for k in 0..size / 2 {
+                let (sin, cos) = (angle * k as f64).sin_cos();
+                let a = start + k;
+                let b = a + size / 2;
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        size <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `len` samples of a sine with `amplitude` at `frequency` Hz.
+    fn sine(len: usize, amplitude: f64, frequency: f64, sample_rate: f64) -> Vec<f64> {
+        (0..len)
+            .map(|i| amplitude * (2.0 * PI * frequency * i as f64 / sample_rate).sin())
+            .collect()
+    }
+
+    fn peak(bins: &[(f64, f64)]) -> (usize, f64) {
+        bins.iter()
+            .enumerate()
+            .fold((0, f64::NEG_INFINITY), |best, (index, &(_, db))| {
+                if db > best.1 {
+                    (index, db)
+                } else {
+                    best
+                }
+            })
+    }
+
+    #[test]
+    fn sine_peak_and_amplitude() {
+        let samples = sine(256, 2.0, 32.0, 256.0);
+        let amplitude_db = 20.0 * 2f64.log10();
+        for window in WindowFunction::ALL {
+            let bins = spectrum(&samples, window, 256.0);
+            assert_eq!(bins.len(), 129);
+            let (index, db) = peak(&bins);
+            assert_eq!(index, 32, "{window}");
+            assert_eq!(bins[index].0, 32.0);
+            // The window gain is corrected, so the peak reads the amplitude
+            assert!((db - amplitude_db).abs() < 0.05, "{window}: {db}");
+        }
+    }
+
+    #[test]
+    fn frequencies_reach_nyquist() {
+        let bins = spectrum(&sine(64, 1.0, 5.0, 100.0), WindowFunction::Hann, 100.0);
+        assert_eq!(bins[0].0, 0.0);
+        assert_eq!(bins.last().unwrap().0, 50.0);
+        // Cycles per sample without a sample rate
+        let bins = spectrum(&sine(64, 1.0, 5.0, 100.0), WindowFunction::Hann, 1.0);
+        assert_eq!(bins.last().unwrap().0, 0.5);
+    }
+
+    #[test]
+    fn dc_lands_in_first_bin() {
+        let samples = vec![3.0; 128];
+        let bins = spectrum(&samples, WindowFunction::Rectangular, 1.0);
+        assert!((bins[0].1 - 20.0 * 3f64.log10()).abs() < 1e-9);
+        // Everything else is at the floor
+        assert!(bins[1..].iter().all(|&(_, db)| db < -150.0));
+
+        let bins = spectrum(&samples, WindowFunction::Hann, 1.0);
+        assert!((bins[0].1 - 20.0 * 3f64.log10()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn length_must_be_a_power_of_two() {
+        assert!(spectrum(&[], WindowFunction::Hann, 1.0).is_empty());
+        assert!(spectrum(&[1.0], WindowFunction::Hann, 1.0).is_empty());
+        assert!(spectrum(&[1.0; 6], WindowFunction::Hann, 1.0).is_empty());
+        assert!(spectrum(&[1.0; 100], WindowFunction::Rectangular, 1.0).is_empty());
+        assert_eq!(spectrum(&[1.0; 2], WindowFunction::Hann, 1.0).len(), 2);
+    }
+}
@@ -0,0 +1,154 @@
+/// Summary of the values of a series over a range of records.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SeriesStats {
+    /// Number of values, gaps excluded.
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub rms: f64,
+    /// Values per second, from the receive times of the first and last value.
+    pub sample_rate: Option<f64>,
+}
+
+impl SeriesStats {
+    /// Compute the statistics of `(time, value)` pairs. NaN values are gaps
+    /// and are skipped. The mean and variance are updated per value
+    /// (Welford), which stays accurate for small changes on a large offset.
+    pub fn compute(samples: impl Iterator<Item = (f64, f64)>) -> Self {
+        let mut stats = Self {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            ..Self::default()
+        };
+        // Sum of squared differences from the mean
+        let mut squares = 0.0;
+        let mut first_time = None;
+        let mut last_time = 0.0;
+
+        for (time, value) in samples.filter(|(_, value)| value.is_finite()) {
+            stats.count += 1;
+            stats.min = stats.min.min(value);
+            stats.max = stats.max.max(value);
+            let delta = value - stats.mean;
+            stats.mean += delta / stats.count as f64;
+            squares += delta * (value - stats.mean);
+            first_time.get_or_insert(time);
+            last_time = time;
+        }
+        if stats.count == 0 {
+            return Self::default();
+        }
+
+        let count = stats.count as f64;
+        let variance = squares / count;
+        stats.std_dev = variance.sqrt();
+        stats.rms = (stats.mean * stats.mean + variance).sqrt();
+        stats.sample_rate = first_time
+            .map(|first| last_time - first)
+            .filter(|&span| span > 0.0)
+            .map(|span| (count - 1.0) / span);
+        stats
+    }
+}
+
+/// Count the values falling in each of `counts.len()` equal bins between
+/// `min` and `max`, reusing `counts`. NaN and values outside the range are
+/// not counted; the number of values outside is returned.
+pub fn histogram(
+    values: impl Iterator<Item = f64>,
+    (min, max): (f64, f64),
+    counts: &mut [u64],
+) -> u64 {
+    counts.fill(0);
+    let bins = counts.len();
+    let width = (max - min) / bins as f64;
+    let mut outside = 0;
+    for value in values.filter(|value| value.is_finite()) {
+        if value < min || value > max || bins == 0 {
+            outside += 1;
+            continue;
+        }
+        // The top edge belongs to the last bin
+        let bin = (((value - min) / width) as usize).min(bins - 1);
+        counts[bin] += 1;
+    }
+    outside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn known_values() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let stats = SeriesStats::compute(
+            values
+                .iter()
+                .enumerate()
+                .map(|(i, &value)| (i as f64 * 0.5, value)),
+        );
+        assert_eq!(stats.count, 8);
+        assert_eq!(stats.min, 2.0);
+        assert_eq!(stats.max, 9.0);
+        assert!(close(stats.mean, 5.0));
+        assert!(close(stats.std_dev, 2.0));
+        assert!(close(stats.rms, 29f64.sqrt()));
+        assert!(close(stats.sample_rate.unwrap(), 2.0));
+    }
+
+    #[test]
+    fn small_changes_on_large_offset() {
+        let stats = SeriesStats::compute(
+            (0..1000).map(|i| (i as f64, 1e6 + if i % 2 == 0 { 0.01 } else { -0.01 })),
+        );
+        assert!((stats.mean - 1e6).abs() < 1e-6);
+        assert!((stats.std_dev - 0.01).abs() < 1e-9);
+    }
+
+    #[test]
+    fn gaps_are_skipped() {
+        let stats = SeriesStats::compute(
+            [(0.0, f64::NAN), (1.0, 1.0), (2.0, f64::NAN), (3.0, 3.0)].into_iter(),
+        );
+        assert_eq!(stats.count, 2);
+        assert!(close(stats.mean, 2.0));
+        assert!(close(stats.sample_rate.unwrap(), 0.5));
+
+        let empty = SeriesStats::compute([(0.0, f64::NAN)].into_iter());
+        assert_eq!(empty.count, 0);
+        assert_eq!(empty.sample_rate, None);
+    }
+
+    #[test]
+    fn single_value_has_no_rate() {
+        let stats = SeriesStats::compute([(1.0, -3.0)].into_iter());
+        assert_eq!(stats.std_dev, 0.0);
+        assert_eq!(stats.rms, 3.0);
+        assert_eq!(stats.sample_rate, None);
+    }
+
+    #[test]
+    fn histogram_bin_edges() {
+        let mut counts = [0; 4];
+        let values = [0.0, 0.99, 1.0, 2.5, 3.999, 4.0, -0.1, 4.1, f64::NAN];
+        let outside = histogram(values.into_iter(), (0.0, 4.0), &mut counts);
+        // Lower edges belong to their bin, the top edge to the last bin
+        assert_eq!(counts, [2, 1, 1, 2]);
+        assert_eq!(outside, 2);
+    }
+
+    #[test]
+    fn histogram_reuses_counts() {
+        let mut counts = [5; 2];
+        assert_eq!(histogram([0.5].into_iter(), (0.0, 2.0), &mut counts), 0);
+        assert_eq!(counts, [1, 0]);
+        assert_eq!(histogram([1.0].into_iter(), (0.0, 2.0), &mut []), 1);
+    }
+}
@@ -0,0 +1,386 @@
+use super::ChartData;
+use serde::{Deserialize, Serialize};
+
+/// Direction of the level crossing that triggers a capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TriggerEdge {
+    #[default]
+    Rising,
+    Falling,
+}
+
+impl TriggerEdge {
+    pub const ALL: [TriggerEdge; 2] = [TriggerEdge::Rising, TriggerEdge::Falling];
+
+    fn crosses(&self, previous: f64, value: f64, level: f64) -> bool {
+        match self {
+            TriggerEdge::Rising => previous < level && value >= level,
+            TriggerEdge::Falling => previous > level && value <= level,
+        }
+    }
+}
+
+impl std::fmt::Display for TriggerEdge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TriggerEdge::Rising => write!(f, "Rising"),
+            TriggerEdge::Falling => write!(f, "Falling"),
+        }
+    }
+}
+
+/// What is shown while the trigger condition is not met.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TriggerMode {
+    /// Show the latest samples untriggered when no trigger came for a whole
+    /// capture length.
+    #[default]
+    Auto,
+    /// Keep the last triggered capture until the next trigger.
+    Normal,
+    /// Capture once, then hold until armed again.
+    Single,
+}
+
+impl TriggerMode {
+    pub const ALL: [TriggerMode; 3] = [TriggerMode::Auto, TriggerMode::Normal, TriggerMode::Single];
+}
+
+impl std::fmt::Display for TriggerMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TriggerMode::Auto => write!(f, "Auto"),
+            TriggerMode::Normal => write!(f, "Normal"),
+            TriggerMode::Single => write!(f, "Single"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TriggerSettings {
+    pub edge: TriggerEdge,
+    pub level: f64,
+    /// Samples shown before the trigger point.
+    pub pre_trigger: usize,
+    /// Samples in a capture, including the pre-trigger samples.
+    pub length: usize,
+    pub mode: TriggerMode,
+}
+
+impl Default for TriggerSettings {
+    fn default() -> Self {
+        Self {
+            edge: TriggerEdge::Rising,
+            level: 0.0,
+            pre_trigger: 50,
+            length: 200,
+            mode: TriggerMode::Auto,
+        }
+    }
+}
+
+/// Samples of every series around a trigger point.
+pub struct Capture {
+    /// One vector per series, `length` samples each.
+    pub values: Vec<Vec<f64>>,
+    /// Position of the trigger point in the capture.
+    pub pre_trigger: usize,
+    /// False for an untriggered capture in auto mode.
+    pub triggered: bool,
+}
+
+/// Finds trigger points in the chart buffer and keeps the capture shown.
+pub struct Trigger {
+    pub capture: Option<Capture>,
+    /// Single mode waits for a trigger while armed.
+    pub armed: bool,
+    /// Sample index up to which trigger points were searched.
+    searched_to: u64,
+    /// Sample index at the end of the last capture, for the auto timeout.
+    captured_to: u64,
+}
+
+impl Trigger {
+    pub fn new() -> Self {
+        Self {
+            capture: None,
+            armed: true,
+            searched_to: 0,
+            captured_to: 0,
+        }
+    }
+
+    /// Forget the capture and search the buffer from its start again.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Wait in single mode for a trigger after the samples received so far.
+    pub fn arm(&mut self, data: &ChartData) {
+        self.armed = true;
+        self.searched_to = data.first_index() + data.len() as u64;
+    }
+
+    /// Look for a crossing of the trigger level on `series` in the samples
+    /// received since the last call and capture the latest one whose
+    /// post-trigger samples are complete.
+    pub fn update(&mut self, data: &ChartData, series: usize, settings: &TriggerSettings) {
+        let Some(trigger_values) = data.series.get(series).map(|series| &series.values) else {
+            return;
+        };
+        if settings.mode == TriggerMode::Single && !self.armed {
+            return;
+        }
+
+        let length = settings.length.max(2);
+        let pre = settings.pre_trigger.min(length - 1);
+        let post = (length - pre) as u64;
+        let first = data.first_index();
+        let end = first + data.len() as u64;
+
+        // A trigger point needs the sample before it and a full capture around it
+        let from = self.searched_to.max(first + pre.max(1) as u64);
+        let to = (end + 1).saturating_sub(post);
+        if from < to {
+            self.searched_to = to;
+            let found = (from..to).rev().find(|&index| {
+                let i = (index - first) as usize;
+                settings
+                    .edge
+                    .crosses(trigger_values[i - 1], trigger_values[i], settings.level)
+            });
+            if let Some(index) = found {
+                let start = (index - first) as usize - pre;
+                self.capture(data, start, length, pre, true);
+                self.captured_to = index + post;
+                self.armed = false;
+                return;
+            }
+        }
+
+        if settings.mode == TriggerMode::Auto
+            && data.len() >= length
+            && end >= self.captured_to + length as u64
+        {
+            self.capture(data, data.len() - length, length, pre, false);
+            self.captured_to = end;
+        }
+    }
+
+    fn capture(
+        &mut self,
+        data: &ChartData,
+        start: usize,
+        length: usize,
+        pre_trigger: usize,
+        triggered: bool,
+    ) {
+        let values = data
+            .series
+            .iter()
+            .map(|series| {
+                series
+                    .values
+                    .range(start..start + length)
+                    .copied()
+                    .collect()
+            })
+            .collect();
+        self.capture = Some(Capture {
+            values,
+            pre_trigger,
+            triggered,
+        });
+    }
+}
+
+impl Default for Trigger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Square wave of period 10, low for the first half: rising edges at
+    /// 5, 15, 25..., falling edges at 10, 20...
+    fn square(index: u64) -> f64 {
+        if index % 10 >= 5 {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Append samples up to sample index `end`, with the trigger signal in the
+    /// first series and the sample index in the second.
+    fn push_until(data: &mut ChartData, end: u64, signal: fn(u64) -> f64) {
+        while data.first_index() + (data.len() as u64) < end {
+            let index = data.first_index() + data.len() as u64;
+            data.push_record(&[signal(index), index as f64], index as f64);
+        }
+    }
+
+    fn settings(
+        edge: TriggerEdge,
+        pre_trigger: usize,
+        length: usize,
+        mode: TriggerMode,
+    ) -> TriggerSettings {
+        TriggerSettings {
+            edge,
+            level: 0.5,
+            pre_trigger,
+            length,
+            mode,
+        }
+    }
+
+    /// Sample index of the first sample of the capture.
+    fn capture_start(trigger: &Trigger) -> f64 {
+        trigger.capture.as_ref().unwrap().values[1][0]
+    }
+
+    #[test]
+    fn captures_latest_rising_edge() {
+        let mut data = ChartData::new(1000);
+        push_until(&mut data, 30, square);
+        let mut trigger = Trigger::new();
+        trigger.update(
+            &data,
+            0,
+            &settings(TriggerEdge::Rising, 2, 4, TriggerMode::Normal),
+        );
+
+        let capture = trigger.capture.as_ref().unwrap();
+        assert!(capture.triggered);
+        assert_eq!(capture.pre_trigger, 2);
+        assert_eq!(capture.values[0], [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(capture.values[1], [23.0, 24.0, 25.0, 26.0]);
+    }
+
+    #[test]
+    fn captures_falling_edge() {
+        let mut data = ChartData::new(1000);
+        push_until(&mut data, 30, square);
+        let mut trigger = Trigger::new();
+        trigger.update(
+            &data,
+            0,
+            &settings(TriggerEdge::Falling, 2, 4, TriggerMode::Normal),
+        );
+
+        let capture = trigger.capture.as_ref().unwrap();
+        assert_eq!(capture.values[0], [1.0, 1.0, 0.0, 0.0]);
+        assert_eq!(capture.values[1], [18.0, 19.0, 20.0, 21.0]);
+    }
+
+    #[test]
+    fn pre_trigger_offsets_the_capture() {
+        let mut data = ChartData::new(1000);
+        push_until(&mut data, 30, square);
+        for (pre_trigger, length, start) in [(0, 4, 25.0), (5, 8, 20.0)] {
+            let mut trigger = Trigger::new();
+            let settings = settings(
+                TriggerEdge::Rising,
+                pre_trigger,
+                length,
+                TriggerMode::Normal,
+            );
+            trigger.update(&data, 0, &settings);
+            assert_eq!(capture_start(&trigger), start);
+            let capture = trigger.capture.as_ref().unwrap();
+            assert_eq!(capture.values[1].len(), length);
+            assert_eq!(capture.values[0][pre_trigger], 1.0);
+        }
+    }
+
+    #[test]
+    fn waits_for_post_trigger_samples() {
+        // The oldest samples have left the buffer
+        let mut data = ChartData::new(20);
+        push_until(&mut data, 27, square);
+        let mut trigger = Trigger::new();
+        let settings = settings(TriggerEdge::Rising, 2, 6, TriggerMode::Normal);
+        trigger.update(&data, 0, &settings);
+        // The edge at 25 lacks samples after it, so the one at 15 is shown
+        assert_eq!(capture_start(&trigger), 13.0);
+
+        push_until(&mut data, 29, square);
+        trigger.update(&data, 0, &settings);
+        assert_eq!(capture_start(&trigger), 23.0);
+    }
+
+    #[test]
+    fn auto_shows_latest_samples_after_timeout() {
+        let mut data = ChartData::new(1000);
+        let mut trigger = Trigger::new();
+        let settings = settings(TriggerEdge::Rising, 1, 4, TriggerMode::Auto);
+
+        push_until(&mut data, 3, |_| 0.0);
+        trigger.update(&data, 0, &settings);
+        assert!(trigger.capture.is_none());
+
+        push_until(&mut data, 4, |_| 0.0);
+        trigger.update(&data, 0, &settings);
+        assert!(!trigger.capture.as_ref().unwrap().triggered);
+        assert_eq!(capture_start(&trigger), 0.0);
+
+        // Held for a whole capture length before the next one
+        push_until(&mut data, 7, |_| 0.0);
+        trigger.update(&data, 0, &settings);
+        assert_eq!(capture_start(&trigger), 0.0);
+        push_until(&mut data, 8, |_| 0.0);
+        trigger.update(&data, 0, &settings);
+        assert_eq!(capture_start(&trigger), 4.0);
+    }
+
+    #[test]
+    fn normal_holds_last_capture() {
+        let mut data = ChartData::new(1000);
+        let mut trigger = Trigger::new();
+        let settings = settings(TriggerEdge::Rising, 2, 4, TriggerMode::Normal);
+
+        push_until(&mut data, 20, |_| 0.0);
+        trigger.update(&data, 0, &settings);
+        assert!(trigger.capture.is_none());
+
+        // One edge at 25, then a flat signal
+        push_until(&mut data, 100, |index| if index >= 25 { 1.0 } else { 0.0 });
+        trigger.update(&data, 0, &settings);
+        assert_eq!(capture_start(&trigger), 23.0);
+        push_until(&mut data, 200, |_| 1.0);
+        trigger.update(&data, 0, &settings);
+        assert!(trigger.capture.as_ref().unwrap().triggered);
+        assert_eq!(capture_start(&trigger), 23.0);
+    }
+
+    #[test]
+    fn single_waits_to_be_armed() {
+        let mut data = ChartData::new(1000);
+        let mut trigger = Trigger::new();
+        let settings = settings(TriggerEdge::Rising, 2, 4, TriggerMode::Single);
+
+        push_until(&mut data, 10, square);
+        trigger.update(&data, 0, &settings);
+        assert_eq!(capture_start(&trigger), 3.0);
+        assert!(!trigger.armed);
+
+        // The edge at 15 comes while disarmed
+        push_until(&mut data, 20, square);
+        trigger.update(&data, 0, &settings);
+        assert_eq!(capture_start(&trigger), 3.0);
+
+        // Arming only looks at samples received afterwards
+        trigger.arm(&data);
+        trigger.update(&data, 0, &settings);
+        assert_eq!(capture_start(&trigger), 3.0);
+        push_until(&mut data, 30, square);
+        trigger.update(&data, 0, &settings);
+        assert_eq!(capture_start(&trigger), 23.0);
+        assert!(!trigger.armed);
+    }
+}
@@ -0,0 +1,295 @@
+use serde::{Deserialize, Serialize};
+
+/// Check value that can be appended to an outgoing frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ChecksumKind {
+    #[default]
+    None,
+    /// CRC-16/MODBUS: polynomial 0x8005 (reflected), initial value 0xFFFF.
+    Crc16Modbus,
+    /// CRC-16/CCITT-FALSE: polynomial 0x1021, initial value 0xFFFF.
+    Crc16Ccitt,
+    /// CRC-32 as used by Ethernet and ZIP.
+    Crc32,
+    /// CRC-8/SMBUS: polynomial 0x07, initial value 0.
+    Crc8,
+    /// XOR of all bytes.
+    Xor,
+    /// Sum of all bytes modulo 256.
+    Sum8,
+}
+
+impl ChecksumKind {
+    pub const ALL: [ChecksumKind; 7] = [
+        ChecksumKind::None,
+        ChecksumKind::Crc16Modbus,
+        ChecksumKind::Crc16Ccitt,
+        ChecksumKind::Crc32,
+        ChecksumKind::Crc8,
+        ChecksumKind::Xor,
+        ChecksumKind::Sum8,
+    ];
+
+    /// Number of bytes the check value takes on the wire.
+    pub fn width(&self) -> usize {
+        match self {
+            ChecksumKind::None => 0,
+            ChecksumKind::Crc8 | ChecksumKind::Xor | ChecksumKind::Sum8 => 1,
+            ChecksumKind::Crc16Modbus | ChecksumKind::Crc16Ccitt => 2,
+            ChecksumKind::Crc32 => 4,
+        }
+    }
+
+    /// Byte order the check value is usually sent in.
+    pub fn usual_byte_order(&self) -> ByteOrder {
+        match self {
+            ChecksumKind::Crc16Modbus => ByteOrder::LittleEndian,
+            _ => ByteOrder::BigEndian,
+        }
+    }
+
+    pub fn compute(&self, data: &[u8]) -> u32 {
+        match self {
+            ChecksumKind::None => 0,
+            ChecksumKind::Crc16Modbus => crc16_modbus(data) as u32,
+            ChecksumKind::Crc16Ccitt => crc16_ccitt(data) as u32,
+            ChecksumKind::Crc32 => crc32(data),
+            ChecksumKind::Crc8 => crc8(data) as u32,
+            ChecksumKind::Xor => data.iter().fold(0u8, |acc, &byte| acc ^ byte) as u32,
+            ChecksumKind::Sum8 => data.iter().fold(0u8, |acc, &byte| acc.wrapping_add(byte)) as u32,
+        }
+    }
+}
+
+impl std::fmt::Display for ChecksumKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChecksumKind::None => write!(f, "None"),
+            ChecksumKind::Crc16Modbus => write!(f, "CRC-16/MODBUS"),
+            ChecksumKind::Crc16Ccitt => write!(f, "CRC-16/CCITT"),
+            ChecksumKind::Crc32 => write!(f, "CRC-32"),
+            ChecksumKind::Crc8 => write!(f, "CRC-8"),
+            ChecksumKind::Xor => write!(f, "XOR"),
+            ChecksumKind::Sum8 => write!(f, "Sum mod 256"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ByteOrder {
+    #[default]
+    BigEndian,
+    LittleEndian,
+}
+
+impl std::fmt::Display for ByteOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ByteOrder::BigEndian => write!(f, "Big endian"),
+            ByteOrder::LittleEndian => write!(f, "Little endian"),
+        }
+    }
+}
+
+/// Check value appended to the payload before it is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Trailer {
+    pub kind: ChecksumKind,
+    pub byte_order: ByteOrder,
+    /// Leading bytes left out of the calculation, e.g. a start-of-frame byte.
+    pub skip_start: usize,
+    /// Trailing bytes left out of the calculation.
+    pub skip_end: usize,
+}
+
+impl Trailer {
+    pub fn is_enabled(&self) -> bool {
+        self.kind != ChecksumKind::None
+    }
+
+    /// Compute the check value over the configured range of `data` and
+    /// append it.
+    pub fn append(&self, data: &mut Vec<u8>) -> Result<(), String> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        if self.skip_start + self.skip_end > data.len() {
+            return Err(format!(
+                "Checksum range skips {} bytes but the payload has only {}",
+                self.skip_start + self.skip_end,
+                data.len()
+            ));
+        }
+
+        let value = self
+            .kind
+            .compute(&data[self.skip_start..data.len() - self.skip_end]);
+        let width = self.kind.width();
+        match self.byte_order {
+            ByteOrder::BigEndian => data.extend_from_slice(&value.to_be_bytes()[4 - width..]),
+            ByteOrder::LittleEndian => data.extend_from_slice(&value.to_le_bytes()[..width]),
+        }
+        Ok(())
+    }
+
+    /// True when `frame` ends with the check value of what comes before
+    /// it, computed over the configured range.
+    pub fn verify(&self, frame: &[u8]) -> bool {
+        let Some(payload_length) = frame.len().checked_sub(self.kind.width()) else {
+            return false;
+        };
+        let mut expected = frame[..payload_length].to_vec();
+        self.append(&mut expected).is_ok() && expected == frame
+    }
+}
+
+pub fn crc16_modbus(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFFu16, |mut crc, &byte| {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+        }
+        crc
+    })
+}
+
+pub fn crc16_ccitt(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFFu16, |mut crc, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// CRC-16/XMODEM: polynomial 0x1021, initial value 0, as used by XMODEM
+/// blocks and ZMODEM hex headers.
+pub fn crc16_xmodem(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |mut crc, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// CRC-32 (IEEE 802.3), as used by Ethernet, ZIP and ZMODEM binary frames.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(0xFFFF_FFFFu32, |mut crc, &byte| {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+        crc
+    })
+}
+
+pub fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECK_INPUT: &[u8] = b"123456789";
+
+    #[test]
+    fn check_values() {
+        assert_eq!(crc16_modbus(CHECK_INPUT), 0x4B37);
+        assert_eq!(crc16_ccitt(CHECK_INPUT), 0x29B1);
+        assert_eq!(crc16_xmodem(CHECK_INPUT), 0x31C3);
+        assert_eq!(crc32(CHECK_INPUT), 0xCBF4_3926);
+        assert_eq!(crc8(CHECK_INPUT), 0xF4);
+    }
+
+    #[test]
+    fn trailer_byte_order() {
+        let mut trailer = Trailer {
+            kind: ChecksumKind::Crc16Modbus,
+            byte_order: ByteOrder::LittleEndian,
+            ..Trailer::default()
+        };
+        let mut data = CHECK_INPUT.to_vec();
+        trailer.append(&mut data).unwrap();
+        assert_eq!(&data[9..], [0x37, 0x4B]);
+
+        trailer.byte_order = ByteOrder::BigEndian;
+        let mut data = CHECK_INPUT.to_vec();
+        trailer.append(&mut data).unwrap();
+        assert_eq!(&data[9..], [0x4B, 0x37]);
+
+        trailer.kind = ChecksumKind::Crc32;
+        let mut data = CHECK_INPUT.to_vec();
+        trailer.append(&mut data).unwrap();
+        assert_eq!(&data[9..], [0xCB, 0xF4, 0x39, 0x26]);
+
+        trailer.kind = ChecksumKind::Crc8;
+        let mut data = CHECK_INPUT.to_vec();
+        trailer.append(&mut data).unwrap();
+        assert_eq!(&data[9..], [0xF4]);
+    }
+
+    #[test]
+    fn trailer_skips_framing_bytes() {
+        let trailer = Trailer {
+            kind: ChecksumKind::Crc32,
+            byte_order: ByteOrder::BigEndian,
+            skip_start: 1,
+            skip_end: 2,
+        };
+        let mut data = b"\x02123456789\r\n".to_vec();
+        trailer.append(&mut data).unwrap();
+        assert_eq!(&data[..12], b"\x02123456789\r\n");
+        assert_eq!(&data[12..], 0xCBF4_3926u32.to_be_bytes());
+    }
+
+    #[test]
+    fn trailer_range_longer_than_payload() {
+        let trailer = Trailer {
+            kind: ChecksumKind::Xor,
+            skip_start: 2,
+            skip_end: 2,
+            ..Trailer::default()
+        };
+        let mut data = vec![1, 2, 3];
+        assert!(trailer.append(&mut data).is_err());
+        assert_eq!(data, [1, 2, 3]);
+    }
+
+    #[test]
+    fn disabled_trailer_appends_nothing() {
+        let mut data = CHECK_INPUT.to_vec();
+        Trailer::default().append(&mut data).unwrap();
+        assert_eq!(data, CHECK_INPUT);
+    }
+}
@@ -1,5 +1,8 @@
 use std::io::Result;
-use std::sync::mpsc;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Instant;
 
 #[derive(PartialEq)]
 pub enum EPortState {
@@ -10,11 +13,92 @@ pub enum EPortState {
 /// Events that can be emitted by a communication channel.
 #[allow(dead_code)]
 pub enum CommunicationEvent {
-    DataReceived(Vec<u8>),
+    /// Bytes read from the channel and the time they were read.
+    DataReceived(Vec<u8>, Instant),
     ConnectionClosed,
     Error(String),
 }
 
+/// A message in the send queue of a channel, with the queue position of its
+/// first byte.
+pub type QueuedData = (u64, Vec<u8>);
+
+/// Counters shared between the writer thread of a channel and the senders
+/// feeding its queue. Queue positions count bytes since the channel opened.
+#[derive(Default)]
+pub struct WriteProgress {
+    /// Bytes handed to the queue.
+    queued: AtomicU64,
+    /// Bytes written out, or dropped by a discard request.
+    written: AtomicU64,
+    /// Queue positions whose bytes a sender withdrew before they were written.
+    discard: Mutex<Vec<Range<u64>>>,
+    /// The writer stopped; nothing more will be written.
+    closed: AtomicBool,
+}
+
+impl WriteProgress {
+    /// Count bytes the writer has finished with.
+    pub fn add_written(&self, count: usize) {
+        self.written.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// Queue positions to drop, requested since the last call.
+    pub fn take_discard(&self) -> Vec<Range<u64>> {
+        self.discard
+            .lock()
+            .map(|mut discard| std::mem::take(&mut *discard))
+            .unwrap_or_default()
+    }
+
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Feeds the send queue of a channel and follows how far the writer got,
+/// for producers running on their own thread.
+#[derive(Clone)]
+pub struct DataSender {
+    tx: mpsc::Sender<QueuedData>,
+    progress: Arc<WriteProgress>,
+}
+
+impl DataSender {
+    pub fn new(tx: mpsc::Sender<QueuedData>, progress: Arc<WriteProgress>) -> Self {
+        Self { tx, progress }
+    }
+
+    /// Queue `data` and return the queue position just after it. The data
+    /// left the channel once `written()` reached that position.
+    pub fn send(&self, data: Vec<u8>) -> std::result::Result<u64, mpsc::SendError<Vec<u8>>> {
+        let len = data.len() as u64;
+        let start = self.progress.queued.fetch_add(len, Ordering::Relaxed);
+        if let Err(mpsc::SendError((_, data))) = self.tx.send((start, data)) {
+            self.progress.queued.fetch_sub(len, Ordering::Relaxed);
+            return Err(mpsc::SendError(data));
+        }
+        Ok(start + len)
+    }
+
+    /// Queue position up to which the writer has finished.
+    pub fn written(&self) -> u64 {
+        self.progress.written.load(Ordering::Relaxed)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.progress.closed.load(Ordering::Relaxed)
+    }
+
+    /// Ask the writer to drop what is not yet written of the data this
+    /// sender queued at `ranges`. Data of other senders is kept.
+    pub fn discard(&self, ranges: impl IntoIterator<Item = Range<u64>>) {
+        if let Ok(mut discard) = self.progress.discard.lock() {
+            discard.extend(ranges);
+        }
+    }
+}
+
 /// Trait for managing a generic byte stream communication channel (serial, TCP, etc.).
 pub trait CommunicationManager: Send {
     /// Start the communication and background thread, sending events to the provided channel.
@@ -29,6 +113,12 @@ pub trait CommunicationManager: Send {
     /// Send data asynchronously to the channel.
     fn send_data(&mut self, data: Vec<u8>) -> Result<()>;
 
+    /// (Optional) A sender feeding the same queue as `send_data`, for producers
+    /// running on their own thread. Returns `None` if the channel is not open.
+    fn data_sender(&self) -> Option<DataSender> {
+        None
+    }
+
     /// List available connections (e.g., serial ports, network endpoints).
     fn get_available_connections(&self) -> Vec<String>;
 
@@ -37,3 +127,27 @@ pub trait CommunicationManager: Send {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discard_is_taken_once() {
+        let (tx, rx) = mpsc::channel();
+        let progress = Arc::new(WriteProgress::default());
+        let file = DataSender::new(tx, Arc::clone(&progress));
+        let other = file.clone();
+
+        assert_eq!(file.send(vec![1, 2, 3]), Ok(3));
+        assert_eq!(other.send(vec![4]), Ok(4));
+        assert_eq!(file.send(vec![5, 6]), Ok(6));
+        let queued: Vec<QueuedData> = rx.try_iter().collect();
+        assert_eq!(queued, [(0, vec![1, 2, 3]), (3, vec![4]), (4, vec![5, 6])]);
+
+        file.discard([0..3, 4..6]);
+        assert_eq!(progress.take_discard(), [0..3, 4..6]);
+        // A later send is not affected by an earlier cancel
+        assert!(progress.take_discard().is_empty());
+    }
+}
@@ -0,0 +1,252 @@
+use crate::communicationtrait::DataSender;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the sender checks how far the port writer got.
+const WRITE_POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+/// Pacing applied while streaming a file.
+#[derive(Debug, Clone, Copy)]
+pub struct FileSendOptions {
+    /// Maximum number of bytes handed to the port at once.
+    pub chunk_size: usize,
+    /// Pause after every chunk.
+    pub chunk_delay: Duration,
+    /// Pause after every `\n`. When non-zero, chunks never span a line end.
+    pub line_delay: Duration,
+}
+
+/// State of a file transfer as seen by the GUI.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileSendState {
+    Sending,
+    Done,
+    Cancelled,
+    Failed(String),
+}
+
+/// Streams a file through a port's data sender on a background thread.
+/// Progress counts bytes the port writer actually wrote, and at most two
+/// chunks are queued at a time so a cancel takes effect right away.
+pub struct FileSender {
+    cancel: Arc<AtomicBool>,
+    sent: Arc<AtomicU64>,
+    state: Arc<Mutex<FileSendState>>,
+    total: u64,
+    started: Instant,
+    finished: Arc<Mutex<Option<Instant>>>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl FileSender {
+    pub fn start(
+        path: &str,
+        sender: DataSender,
+        options: FileSendOptions,
+        ctx: egui::Context,
+    ) -> std::io::Result<Self> {
+        let data = std::fs::read(path)?;
+        let total = data.len() as u64;
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let sent = Arc::new(AtomicU64::new(0));
+        let state = Arc::new(Mutex::new(FileSendState::Sending));
+        let finished = Arc::new(Mutex::new(None));
+
+        let cancel_clone = Arc::clone(&cancel);
+        let sent_clone = Arc::clone(&sent);
+        let state_clone = Arc::clone(&state);
+        let finished_clone = Arc::clone(&finished);
+        let handle = thread::spawn(move || {
+            let result = Self::stream(&data, &sender, options, &cancel_clone, &sent_clone);
+            if let Ok(mut state) = state_clone.lock() {
+                *state = result;
+            }
+            if let Ok(mut finished) = finished_clone.lock() {
+                *finished = Some(Instant::now());
+            }
+            ctx.request_repaint();
+        });
+
+        Ok(Self {
+            cancel,
+            sent,
+            state,
+            total,
+            started: Instant::now(),
+            finished,
+            handle: Some(handle),
+        })
+    }
+
+    fn stream(
+        data: &[u8],
+        sender: &DataSender,
+        options: FileSendOptions,
+        cancel: &AtomicBool,
+        sent: &AtomicU64,
+    ) -> FileSendState {
+        let chunk_size = options.chunk_size.max(1);
+        let lines: Vec<&[u8]> = if options.line_delay.is_zero() {
+            vec![data]
+        } else {
+            data.split_inclusive(|&b| b == b'\n').collect()
+        };
+        // Delays are timed from when a chunk was written, not queued
+        let paced = !options.chunk_delay.is_zero() || !options.line_delay.is_zero();
+
+        let mut progress = WriteTracker {
+            sender,
+            cancel,
+            sent,
+            start: None,
+            queued: 0,
+            in_flight: Vec::new(),
+        };
+        let mut previous_end = 0;
+        for line in lines {
+            for chunk in line.chunks(chunk_size) {
+                if cancel.load(Ordering::Relaxed) {
+                    progress.withdraw();
+                    return FileSendState::Cancelled;
+                }
+                let Ok(end) = sender.send(chunk.to_vec()) else {
+                    return FileSendState::Failed("Port closed".to_string());
+                };
+                progress.queued(end, chunk.len());
+
+                // Keep the port busy with the next chunk, but no more
+                let wait_for = if paced { end } else { previous_end };
+                if let Err(state) = progress.wait_until(wait_for) {
+                    return state;
+                }
+                previous_end = end;
+                sleep_unless_cancelled(options.chunk_delay, cancel);
+            }
+            if line.ends_with(b"\n") {
+                sleep_unless_cancelled(options.line_delay, cancel);
+            }
+        }
+        match progress.wait_until(previous_end) {
+            Ok(()) => FileSendState::Done,
+            Err(state) => state,
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                eprintln!("File send thread panicked");
+            }
+        }
+    }
+
+    pub fn state(&self) -> FileSendState {
+        self.state
+            .lock()
+            .map(|state| state.clone())
+            .unwrap_or(FileSendState::Failed("State unavailable".to_string()))
+    }
+
+    pub fn sent_bytes(&self) -> u64 {
+        self.sent.load(Ordering::Relaxed)
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.total
+    }
+
+    /// Time spent so far, or the total duration once the transfer ended.
+    pub fn elapsed(&self) -> Duration {
+        match self.finished.lock().ok().and_then(|finished| *finished) {
+            Some(end) => end - self.started,
+            None => self.started.elapsed(),
+        }
+    }
+
+    /// Average rate in bytes per second.
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed().as_secs_f64();
+        if secs > 0.0 {
+            self.sent_bytes() as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Follows the file's bytes through the port's send queue.
+struct WriteTracker<'a> {
+    sender: &'a DataSender,
+    cancel: &'a AtomicBool,
+    sent: &'a AtomicU64,
+    /// Queue position of the first byte of the file.
+    start: Option<u64>,
+    /// Bytes of the file queued so far.
+    queued: u64,
+    /// Queue positions of the chunks that may not be written yet.
+    in_flight: Vec<Range<u64>>,
+}
+
+impl WriteTracker<'_> {
+    /// Record a chunk of `len` bytes queued up to position `end`.
+    fn queued(&mut self, end: u64, len: usize) {
+        let start = end - len as u64;
+        self.start.get_or_insert(start);
+        self.queued += len as u64;
+        let written = self.sender.written();
+        self.in_flight.retain(|chunk| chunk.end > written);
+        self.in_flight.push(start..end);
+    }
+
+    /// Drop the file's chunks the writer has not written yet, leaving the
+    /// data of other senders in the queue.
+    fn withdraw(&self) {
+        self.sender.discard(self.in_flight.iter().cloned());
+    }
+
+    /// Wait until the writer reached queue position `position`, updating the
+    /// sent count meanwhile.
+    fn wait_until(&self, position: u64) -> Result<(), FileSendState> {
+        loop {
+            let written = self.sender.written();
+            if let Some(start) = self.start {
+                let sent = written.saturating_sub(start).min(self.queued);
+                self.sent.store(sent, Ordering::Relaxed);
+            }
+            if written >= position {
+                return Ok(());
+            }
+            if self.cancel.load(Ordering::Relaxed) {
+                self.withdraw();
+                return Err(FileSendState::Cancelled);
+            }
+            if self.sender.is_closed() {
+                return Err(FileSendState::Failed("Port closed".to_string()));
+            }
+            thread::sleep(WRITE_POLL_INTERVAL);
+        }
+    }
+}
+
+/// Sleep for `duration` in short slices so a cancel request is honoured quickly.
+fn sleep_unless_cancelled(duration: Duration, cancel: &AtomicBool) {
+    let deadline = Instant::now() + duration;
+    loop {
+        let now = Instant::now();
+        if now >= deadline || cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        thread::sleep((deadline - now).min(Duration::from_millis(20)));
+    }
+}
+
+impl Drop for FileSender {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
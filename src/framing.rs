@@ -0,0 +1,329 @@
+use crate::checksum::ByteOrder;
+use crate::payload::parse_escaped;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// How received chunks are reassembled into packets before they are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FramingMode {
+    /// Every chunk read from the port is a packet.
+    #[default]
+    None,
+    /// A packet ends with a delimiter sequence.
+    Delimiter,
+    /// Every packet has the same length.
+    FixedLength,
+    /// A length field inside the header gives the packet length.
+    LengthField,
+    /// A packet ends when no byte arrived for a while.
+    IdleGap,
+}
+
+impl FramingMode {
+    pub const ALL: [FramingMode; 5] = [
+        FramingMode::None,
+        FramingMode::Delimiter,
+        FramingMode::FixedLength,
+        FramingMode::LengthField,
+        FramingMode::IdleGap,
+    ];
+}
+
+impl std::fmt::Display for FramingMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FramingMode::None => write!(f, "None (as read)"),
+            FramingMode::Delimiter => write!(f, "Delimiter"),
+            FramingMode::FixedLength => write!(f, "Fixed length"),
+            FramingMode::LengthField => write!(f, "Length field"),
+            FramingMode::IdleGap => write!(f, "Idle gap"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FramingSettings {
+    pub mode: FramingMode,
+    /// Sequence ending a packet, with `\r\n`-style escapes. It stays part of
+    /// the packet.
+    pub delimiter: String,
+    pub frame_length: usize,
+    /// Position of the length field from the start of the packet.
+    pub length_offset: usize,
+    /// Size of the length field in bytes: 1, 2 or 4.
+    pub length_size: usize,
+    pub length_byte_order: ByteOrder,
+    /// Added to the bytes after the length field to get the rest of the
+    /// packet, e.g. 2 for a trailing CRC or a negative value when the field
+    /// counts the header too.
+    pub length_adjust: i64,
+    pub idle_gap_ms: u64,
+    /// Packets are cut at this length if their end was not found.
+    pub max_frame_length: usize,
+}
+
+impl Default for FramingSettings {
+    fn default() -> Self {
+        Self {
+            mode: FramingMode::None,
+            delimiter: "\\n".to_string(),
+            frame_length: 16,
+            length_offset: 0,
+            length_size: 1,
+            length_byte_order: ByteOrder::BigEndian,
+            length_adjust: 0,
+            idle_gap_ms: 20,
+            max_frame_length: 4096,
+        }
+    }
+}
+
+/// Reassembles the arbitrary chunks delivered by the serial thread into
+/// packets, following [`FramingSettings`].
+pub struct FrameSplitter {
+    settings: FramingSettings,
+    delimiter: Vec<u8>,
+    buffer: Vec<u8>,
+    /// Receive time of the last byte in `buffer`.
+    last_received: Option<Instant>,
+}
+
+impl FrameSplitter {
+    pub fn new(settings: &FramingSettings) -> Result<Self, String> {
+        let delimiter =
+            parse_escaped(&settings.delimiter).map_err(|e| format!("Delimiter: {e}"))?;
+        match settings.mode {
+            FramingMode::Delimiter if delimiter.is_empty() => {
+                return Err("Delimiter must not be empty".to_string())
+            }
+            FramingMode::FixedLength if settings.frame_length == 0 => {
+                return Err("Frame length must not be 0".to_string())
+            }
+            FramingMode::LengthField if ![1, 2, 4].contains(&settings.length_size) => {
+                return Err("Length field must be 1, 2 or 4 bytes".to_string())
+            }
+            FramingMode::IdleGap if settings.idle_gap_ms == 0 => {
+                return Err("Idle gap must not be 0".to_string())
+            }
+            _ => {}
+        }
+        if settings.max_frame_length == 0 {
+            return Err("Maximum frame length must not be 0".to_string());
+        }
+
+        Ok(Self {
+            settings: settings.clone(),
+            delimiter,
+            buffer: Vec::new(),
+            last_received: None,
+        })
+    }
+
+    /// Add a received chunk and return the packets completed by it.
+    pub fn push(&mut self, data: &[u8], received: Instant) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+        if self.settings.mode == FramingMode::None {
+            frames.push(data.to_vec());
+            return frames;
+        }
+
+        // A gap before this chunk ends the pending packet
+        if self.settings.mode == FramingMode::IdleGap {
+            frames.extend(self.poll(received));
+        }
+        self.buffer.extend_from_slice(data);
+        self.last_received = Some(received);
+
+        while let Some(length) = self.next_frame_length() {
+            frames.push(self.buffer.drain(..length).collect());
+        }
+        // Cut packets whose end does not come
+        while self.buffer.len() >= self.settings.max_frame_length {
+            let length = self.settings.max_frame_length;
+            frames.push(self.buffer.drain(..length).collect());
+        }
+        frames
+    }
+
+    /// In idle gap mode, return the pending packet once no byte arrived
+    /// for the gap.
+    pub fn poll(&mut self, now: Instant) -> Option<Vec<u8>> {
+        if now < self.pending_deadline()? {
+            return None;
+        }
+        Some(std::mem::take(&mut self.buffer))
+    }
+
+    /// In idle gap mode, when the pending packet is complete unless more
+    /// bytes arrive.
+    pub fn pending_deadline(&self) -> Option<Instant> {
+        if self.settings.mode != FramingMode::IdleGap || self.buffer.is_empty() {
+            return None;
+        }
+        let gap = Duration::from_millis(self.settings.idle_gap_ms);
+        Some(self.last_received? + gap)
+    }
+
+    /// Drop a partly received packet.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.last_received = None;
+    }
+
+    /// Length of the complete packet at the start of the buffer.
+    fn next_frame_length(&mut self) -> Option<usize> {
+        match self.settings.mode {
+            FramingMode::None | FramingMode::IdleGap => None,
+            FramingMode::Delimiter => self
+                .buffer
+                .windows(self.delimiter.len())
+                .position(|window| window == self.delimiter)
+                .map(|position| position + self.delimiter.len()),
+            FramingMode::FixedLength => (self.buffer.len() >= self.settings.frame_length)
+                .then_some(self.settings.frame_length),
+            FramingMode::LengthField => loop {
+                let header = self.settings.length_offset + self.settings.length_size;
+                let field = self.buffer.get(self.settings.length_offset..header)?;
+                let add_byte = |value: u64, &byte: &u8| (value << 8) | byte as u64;
+                let value = match self.settings.length_byte_order {
+                    ByteOrder::BigEndian => field.iter().fold(0, add_byte),
+                    ByteOrder::LittleEndian => field.iter().rev().fold(0, add_byte),
+                };
+                let length = header as i64 + value as i64 + self.settings.length_adjust;
+                if length < header as i64 || length as usize > self.settings.max_frame_length {
+                    // Not a valid header; resynchronize one byte later
+                    self.buffer.remove(0);
+                    continue;
+                }
+                let length = length as usize;
+                return (self.buffer.len() >= length).then_some(length);
+            },
+        }
+    }
+}
+
+impl Default for FrameSplitter {
+    fn default() -> Self {
+        Self::new(&FramingSettings::default()).expect("default framing settings are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn splitter(settings: FramingSettings) -> FrameSplitter {
+        FrameSplitter::new(&settings).unwrap()
+    }
+
+    /// Push `chunks` one after the other and collect the packets.
+    fn split(splitter: &mut FrameSplitter, chunks: &[&[u8]]) -> Vec<Vec<u8>> {
+        let now = Instant::now();
+        chunks
+            .iter()
+            .flat_map(|chunk| splitter.push(chunk, now))
+            .collect()
+    }
+
+    #[test]
+    fn delimiter() {
+        let mut splitter = splitter(FramingSettings {
+            mode: FramingMode::Delimiter,
+            delimiter: "\\r\\n".to_string(),
+            ..FramingSettings::default()
+        });
+        let frames = split(&mut splitter, &[b"ab\r", b"\ncd\r\nef\n\r", b"\n"]);
+        assert_eq!(frames, [&b"ab\r\n"[..], b"cd\r\n", b"ef\n\r\n"]);
+    }
+
+    #[test]
+    fn fixed_length() {
+        let mut splitter = splitter(FramingSettings {
+            mode: FramingMode::FixedLength,
+            frame_length: 3,
+            ..FramingSettings::default()
+        });
+        let frames = split(&mut splitter, &[b"ab", b"cdefg", b"hi"]);
+        assert_eq!(frames, [b"abc", b"def", b"ghi"]);
+    }
+
+    #[test]
+    fn length_field() {
+        // Start byte, 16 bit little endian length of the payload, payload
+        let mut splitter = splitter(FramingSettings {
+            mode: FramingMode::LengthField,
+            length_offset: 1,
+            length_size: 2,
+            length_byte_order: ByteOrder::LittleEndian,
+            ..FramingSettings::default()
+        });
+        let frames = split(
+            &mut splitter,
+            &[&[0x02, 0x03, 0x00, 1], &[2, 3, 0x02, 0x01, 0x00], &[9]],
+        );
+        assert_eq!(
+            frames,
+            [&[0x02, 0x03, 0x00, 1, 2, 3][..], &[0x02, 0x01, 0x00, 9]]
+        );
+    }
+
+    #[test]
+    fn length_field_resynchronizes() {
+        // The length counts the whole packet: a value below the header size
+        // or above the maximum cannot be a header
+        let mut splitter = splitter(FramingSettings {
+            mode: FramingMode::LengthField,
+            length_size: 1,
+            length_adjust: -1,
+            max_frame_length: 8,
+            ..FramingSettings::default()
+        });
+        let frames = split(&mut splitter, &[&[0x00, 0xFF, 0x03, 7, 8, 0x02, 9]]);
+        assert_eq!(frames, [&[0x03, 7, 8][..], &[0x02, 9]]);
+    }
+
+    #[test]
+    fn max_frame_length_cuts_packets() {
+        let mut splitter = splitter(FramingSettings {
+            mode: FramingMode::Delimiter,
+            max_frame_length: 4,
+            ..FramingSettings::default()
+        });
+        let frames = split(&mut splitter, &[b"abcdefghij", b"\n"]);
+        assert_eq!(frames, [&b"abcd"[..], b"efgh", b"ij\n"]);
+    }
+
+    #[test]
+    fn idle_gap() {
+        let mut splitter = splitter(FramingSettings {
+            mode: FramingMode::IdleGap,
+            idle_gap_ms: 20,
+            ..FramingSettings::default()
+        });
+        let start = Instant::now();
+        let gap = Duration::from_millis(20);
+        assert!(splitter.push(b"ab", start).is_empty());
+        assert!(splitter.push(b"c", start + gap / 2).is_empty());
+        assert_eq!(splitter.pending_deadline(), Some(start + gap / 2 + gap));
+        assert_eq!(splitter.poll(start + gap), None);
+        assert_eq!(splitter.poll(start + gap * 2), Some(b"abc".to_vec()));
+        assert_eq!(splitter.pending_deadline(), None);
+
+        // A chunk after the gap first completes the pending packet
+        assert!(splitter.push(b"d", start + gap * 3).is_empty());
+        assert_eq!(splitter.push(b"e", start + gap * 5), [b"d"]);
+    }
+
+    #[test]
+    fn clear_drops_partial_packet() {
+        let mut splitter = splitter(FramingSettings {
+            mode: FramingMode::FixedLength,
+            frame_length: 3,
+            ..FramingSettings::default()
+        });
+        assert!(split(&mut splitter, &[b"ab"]).is_empty());
+        splitter.clear();
+        assert_eq!(split(&mut splitter, &[b"cde"]), [b"cde"]);
+    }
+}
@@ -1,6 +1,16 @@
+use crate::chart::alarm::Threshold;
+use crate::chart::binary::BinaryFrameFormat;
+use crate::chart::expression::DerivedSeries;
+use crate::chart::parser::ChartParseMode;
+use crate::chart::spectrum::WindowFunction;
+use crate::chart::trigger::TriggerSettings;
+use crate::chart::{ChartDisplay, ChartLayout, ChartXAxis};
+use crate::framing::FramingSettings;
+use crate::macros::Macro;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppSettings {
     pub max_log_string_length: usize,
     pub log_file_default_name: String,
@@ -12,8 +22,54 @@ pub struct AppSettings {
     pub window_width: f32,
     pub window_height: f32,
     pub byte_mode: bool,
+    /// How received chunks are grouped into packets for display.
+    pub framing: FramingSettings,
     pub show_chart_panel: bool,
     pub show_text_panel: bool,
+    /// Settings saved before parse modes existed plotted a plain stream of
+    /// values; they keep doing so. New installs start with columns.
+    #[serde(default = "legacy_parse_mode")]
+    pub chart_parse_mode: ChartParseMode,
+    /// Number of records kept when the X axis shows samples.
+    pub chart_buffer_size: usize,
+    /// Characters separating the values of a record, with `\t`-style escapes.
+    pub chart_value_delimiters: String,
+    /// Byte sequence ending a record, with `\r\n`-style escapes.
+    pub chart_record_delimiter: String,
+    /// Optional pattern extracting the numbers of a record.
+    pub chart_number_regex: String,
+    pub chart_binary_format: BinaryFrameFormat,
+    /// Number of samples shown while the chart follows the latest data.
+    pub chart_window_size: usize,
+    pub chart_x_axis: ChartXAxis,
+    /// Seconds shown and kept when the X axis shows time.
+    pub chart_time_window_secs: f64,
+    pub chart_display: ChartDisplay,
+    pub chart_layout: ChartLayout,
+    /// Number of latest samples transformed by the spectrum view.
+    pub chart_fft_size: usize,
+    pub chart_fft_window: WindowFunction,
+    /// Subtract the mean before the FFT so an offset does not leak into the
+    /// lowest frequencies.
+    pub chart_fft_remove_dc: bool,
+    pub chart_trigger: TriggerSettings,
+    /// Number of latest points drawn by the XY view.
+    pub chart_xy_trail: usize,
+    /// Draw older XY points fainter.
+    pub chart_xy_fade: bool,
+    /// Use the same scale on both XY axes, so circles stay round.
+    pub chart_xy_equal_scale: bool,
+    pub chart_thresholds: Vec<Threshold>,
+    pub chart_histogram_bins: usize,
+    /// Value range of the histogram bins. `None` follows the data.
+    pub chart_histogram_range: Option<(f64, f64)>,
+    pub chart_derived: Vec<DerivedSeries>,
+    pub send_history_size: usize,
+    pub macros: Vec<Macro>,
+}
+
+fn legacy_parse_mode() -> ChartParseMode {
+    ChartParseMode::Stream
 }
 
 impl Default for AppSettings {
@@ -29,8 +85,33 @@ impl Default for AppSettings {
             window_width: 1050.0,
             window_height: 500.0,
             byte_mode: false,
+            framing: FramingSettings::default(),
             show_chart_panel: false,
             show_text_panel: true,
+            chart_parse_mode: ChartParseMode::Columns,
+            chart_buffer_size: 1000,
+            chart_value_delimiters: ", \\t".to_string(),
+            chart_record_delimiter: "\\n".to_string(),
+            chart_number_regex: String::new(),
+            chart_binary_format: BinaryFrameFormat::default(),
+            chart_window_size: 1000,
+            chart_x_axis: ChartXAxis::Samples,
+            chart_time_window_secs: 60.0,
+            chart_display: ChartDisplay::Time,
+            chart_layout: ChartLayout::Overlay,
+            chart_fft_size: 512,
+            chart_fft_window: WindowFunction::Hann,
+            chart_fft_remove_dc: true,
+            chart_trigger: TriggerSettings::default(),
+            chart_xy_trail: 500,
+            chart_xy_fade: true,
+            chart_xy_equal_scale: false,
+            chart_thresholds: Vec::new(),
+            chart_histogram_bins: 50,
+            chart_histogram_range: None,
+            chart_derived: Vec::new(),
+            send_history_size: 100,
+            macros: Vec::new(),
         }
     }
 }
@@ -66,3 +147,18 @@ impl AppSettings {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_mode_of_old_settings() {
+        let old: AppSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(old.chart_parse_mode, ChartParseMode::Stream);
+        assert_eq!(
+            AppSettings::default().chart_parse_mode,
+            ChartParseMode::Columns
+        );
+    }
+}
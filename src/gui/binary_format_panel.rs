@@ -0,0 +1,156 @@
+use super::send_panel::trailer_menu;
+use crate::chart::binary::{BinaryDecoder, BinaryField, BinaryFrameFormat, FieldType};
+use crate::checksum::ByteOrder;
+
+/// Editor for the layout of binary chart frames.
+pub struct BinaryFormatPanel {
+    pub open: bool,
+}
+
+impl BinaryFormatPanel {
+    pub fn new() -> Self {
+        Self { open: false }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, format: &mut BinaryFrameFormat) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Binary frame format")
+            .open(&mut open)
+            .default_size([600.0, 300.0])
+            .show(ctx, |ui| {
+                egui::Grid::new("binary_frame_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Sync header (hex):");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut format.sync)
+                                .hint_text("e.g. AA 55")
+                                .desired_width(150.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Frame length:");
+                        ui.add(
+                            egui::DragValue::new(&mut format.frame_length)
+                                .range(0..=4096)
+                                .suffix(" bytes"),
+                        )
+                        .on_hover_text(
+                            "Including the header and checksum. 0 = header, fields and \
+                             checksum; a larger value skips the bytes before the checksum.",
+                        );
+                        ui.end_row();
+
+                        ui.label("Checksum:");
+                        trailer_menu(ui, &mut format.checksum, "binary_frame_checksum");
+                        ui.end_row();
+                    });
+                ui.separator();
+
+                let mut remove = None;
+                let mut swap = None;
+                egui::ScrollArea::vertical()
+                    .max_height(250.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("binary_fields_grid")
+                            .striped(true)
+                            .num_columns(6)
+                            .show(ui, |ui| {
+                                ui.strong("Name");
+                                ui.strong("Type");
+                                ui.strong("Byte order");
+                                ui.strong("Scale");
+                                ui.strong("Offset");
+                                ui.label("");
+                                ui.end_row();
+
+                                let count = format.fields.len();
+                                for (index, field) in format.fields.iter_mut().enumerate() {
+                                    Self::field_row(ui, index, field);
+
+                                    ui.horizontal(|ui| {
+                                        if ui
+                                            .add_enabled(index > 0, egui::Button::new("⏶"))
+                                            .clicked()
+                                        {
+                                            swap = Some((index - 1, index));
+                                        }
+                                        if ui
+                                            .add_enabled(index + 1 < count, egui::Button::new("⏷"))
+                                            .clicked()
+                                        {
+                                            swap = Some((index, index + 1));
+                                        }
+                                        if ui.button("🗑").clicked() {
+                                            remove = Some(index);
+                                        }
+                                    });
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                if let Some((a, b)) = swap {
+                    format.fields.swap(a, b);
+                }
+                if let Some(index) = remove {
+                    format.fields.remove(index);
+                }
+
+                if ui.button("Add field").clicked() {
+                    let field = BinaryField {
+                        name: format!("ch{}", format.fields.len() + 1),
+                        ..format.fields.last().cloned().unwrap_or_default()
+                    };
+                    format.fields.push(field);
+                }
+
+                if let Err(err) = BinaryDecoder::new(format) {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                }
+            });
+        self.open = open;
+    }
+
+    fn field_row(ui: &mut egui::Ui, index: usize, field: &mut BinaryField) {
+        ui.add(egui::TextEdit::singleline(&mut field.name).desired_width(100.0));
+
+        egui::ComboBox::from_id_salt(("binary_field_type", index))
+            .width(60.0)
+            .selected_text(field.field_type.to_string())
+            .show_ui(ui, |ui| {
+                for field_type in FieldType::ALL {
+                    ui.selectable_value(&mut field.field_type, field_type, field_type.to_string());
+                }
+            });
+
+        egui::ComboBox::from_id_salt(("binary_field_order", index))
+            .selected_text(field.byte_order.to_string())
+            .show_ui(ui, |ui| {
+                for order in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+                    ui.selectable_value(&mut field.byte_order, order, order.to_string());
+                }
+            });
+
+        ui.add(
+            egui::DragValue::new(&mut field.scale)
+                .speed(0.001)
+                .max_decimals(6),
+        );
+        ui.add(
+            egui::DragValue::new(&mut field.offset)
+                .speed(0.1)
+                .max_decimals(6),
+        );
+    }
+}
+
+impl Default for BinaryFormatPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,13 +1,115 @@
-use egui::{containers::Frame, emath, epaint, epaint::PathStroke, pos2, Color32, Pos2, Rect, Vec2};
-use std::collections::VecDeque;
+use super::binary_format_panel::BinaryFormatPanel;
+use super::derived_panel::DerivedPanel;
+use super::plot::{rescale, show_plot, PlotAxes, MARGIN_LEFT};
+use super::threshold_panel::ThresholdPanel;
+use crate::chart::alarm::{AlarmEvent, AlarmMonitor};
+use crate::chart::binary;
+use crate::chart::export::{export_csv, export_png, export_svg, PlotSnapshot, SeriesSnapshot};
+use crate::chart::expression::{DerivedSeries, Expression};
+use crate::chart::parser::{ChartParseMode, ChartParser, ParserConfig, Record};
+use crate::chart::spectrum::{spectrum, WindowFunction, FFT_SIZES};
+use crate::chart::stats::{histogram, SeriesStats};
+use crate::chart::trigger::{Trigger, TriggerEdge, TriggerMode};
+use crate::chart::{ChartData, ChartDisplay, ChartLayout, ChartXAxis, YAxis, MAX_RECORDS};
+use crate::generalsettings::AppSettings;
+use egui::{emath, epaint, epaint::PathStroke, pos2, Color32, Pos2, Rect, Vec2};
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+use std::time::Instant;
 
+/// Time windows offered when the X axis shows time, in seconds.
+const TIME_WINDOWS: [(f64, &str); 3] = [(10.0, "10 s"), (60.0, "60 s"), (600.0, "10 min")];
+
+/// Part of the data shown in the plot. `None` ranges follow the data: the
+/// latest window on the X axis, autoscaling on the Y axes.
 #[derive(Default)]
+struct ChartView {
+    x_range: Option<(f64, f64)>,
+    /// Left axis of the overlay plot.
+    y_range: Option<(f64, f64)>,
+    /// Right axis of the overlay plot.
+    y2_range: Option<(f64, f64)>,
+    /// Y axis of each stacked plot, by series index.
+    stacked_y_ranges: HashMap<usize, (f64, f64)>,
+    paused: bool,
+}
+
+/// Spectrum of an earlier frame, reused while the data and settings it was
+/// computed from are unchanged.
+struct SpectrumCache {
+    /// First sample index and length of the buffer, series count, series,
+    /// FFT size, window and DC removal.
+    key: (u64, usize, usize, usize, usize, WindowFunction, bool),
+    /// Number of samples collected for the FFT.
+    collected: usize,
+    rate: Option<f64>,
+    bins: Vec<(f64, f64)>,
+}
+
+/// The Y axes a pointer interaction on a time plot moves.
+#[derive(Clone, Copy)]
+enum YTarget {
+    /// Both axes of the overlay plot.
+    Overlay,
+    /// The stacked plot of one series.
+    Stacked(usize),
+}
+
 pub struct ChartPanel {
     pub content: VecDeque<char>,
     #[allow(dead_code)]
     should_scroll_to_bottom: bool,
-    samples: VecDeque<f32>,
-    stream_buffer: String, // Buffer to accumulate partial data
+    data: ChartData,
+    parser: ChartParser,
+    /// Settings the parser was last built from.
+    parser_config: ParserConfig,
+    view: ChartView,
+    x_axis: ChartXAxis,
+    layout: ChartLayout,
+    /// Time zero of the time axis.
+    origin: Instant,
+    /// Receive time of the last data given to the parser.
+    last_received: Option<Instant>,
+    /// Export file name without extension.
+    export_path: String,
+    export_status: Option<String>,
+    /// PNG file waiting for the screenshot of the next frame.
+    pending_png: Option<String>,
+    /// Screen area of the plot and its axis labels, for PNG export.
+    chart_rect: Option<Rect>,
+    binary_format_panel: BinaryFormatPanel,
+    show_stats: bool,
+    /// Sample index from which the buffer statistics are computed.
+    stats_start: u64,
+    /// Series shown by the spectrum view.
+    spectrum_series: usize,
+    spectrum_cache: Option<SpectrumCache>,
+    trigger: Trigger,
+    /// Series whose level crossings trigger a capture.
+    trigger_series: usize,
+    /// Series plotted on the X and Y axes of the XY view.
+    xy_series: (usize, usize),
+    threshold_panel: ThresholdPanel,
+    alarms: AlarmMonitor,
+    /// Alarms raised since the chart was cleared.
+    alarm_count: u64,
+    /// An alarm asked to freeze the view at the next frame.
+    pause_requested: bool,
+    /// Series shown by the histogram view.
+    histogram_series: usize,
+    /// Bin counts of the histogram view, kept between frames.
+    histogram_counts: Vec<u64>,
+    derived_panel: DerivedPanel,
+    /// Compiled derived series, by name.
+    derived: Vec<(String, Expression)>,
+    /// Definitions the derived series were last built from.
+    derived_config: Vec<DerivedSeries>,
+}
+
+impl Default for ChartPanel {
+    fn default() -> Self {
+        Self::new(0)
+    }
 }
 
 impl ChartPanel {
@@ -15,199 +117,1797 @@ impl ChartPanel {
         Self {
             content: VecDeque::new(),
             should_scroll_to_bottom: false,
-            samples: VecDeque::new(),
-            stream_buffer: String::new(),
+            data: ChartData::new(AppSettings::default().chart_buffer_size),
+            parser: ChartParser::from_settings(&AppSettings::default())
+                .expect("default chart settings are valid"),
+            parser_config: ParserConfig::from_settings(&AppSettings::default()),
+            view: ChartView::default(),
+            x_axis: ChartXAxis::Samples,
+            layout: ChartLayout::Overlay,
+            origin: Instant::now(),
+            last_received: None,
+            export_path: "chart".to_string(),
+            export_status: None,
+            pending_png: None,
+            chart_rect: None,
+            binary_format_panel: BinaryFormatPanel::new(),
+            show_stats: false,
+            stats_start: 0,
+            spectrum_series: 0,
+            spectrum_cache: None,
+            trigger: Trigger::new(),
+            trigger_series: 0,
+            xy_series: (0, 1),
+            threshold_panel: ThresholdPanel::new(),
+            alarms: AlarmMonitor::default(),
+            alarm_count: 0,
+            pause_requested: false,
+            histogram_series: 0,
+            histogram_counts: Vec::new(),
+            derived_panel: DerivedPanel::new(),
+            derived: Vec::new(),
+            derived_config: Vec::new(),
+        }
+    }
+
+    /// Parse received text. Every record is stamped with the time its last
+    /// byte was received. Its values go to the series selected by their
+    /// column or by their name, depending on the parse mode. Returns the
+    /// alarms raised by the new records.
+    pub fn process_rx(
+        &mut self,
+        message: Vec<u8>,
+        received: Instant,
+        settings: &AppSettings,
+    ) -> Vec<AlarmEvent> {
+        self.apply_limits(settings);
+        self.update_parser(settings);
+        self.update_derived(settings);
+        self.last_received = Some(received);
+        let records = self.parser.push(&message, settings.chart_parse_mode);
+        self.add_records(records, received, settings)
+    }
+
+    /// Add the records the parser held back for more data once nothing was
+    /// received for [`binary::IDLE_GAP`]. Returns the alarms they raised.
+    pub fn poll(&mut self, now: Instant, settings: &AppSettings) -> Vec<AlarmEvent> {
+        let Some(received) = self.last_received else {
+            return Vec::new();
+        };
+        if now < received + binary::IDLE_GAP {
+            return Vec::new();
+        }
+        let records = self.parser.flush(settings.chart_parse_mode);
+        self.add_records(records, received, settings)
+    }
+
+    /// When [`ChartPanel::poll`] should run next, if records are held back.
+    pub fn pending_deadline(&self, settings: &AppSettings) -> Option<Instant> {
+        if !self.parser.is_holding(settings.chart_parse_mode) {
+            return None;
+        }
+        Some(self.last_received? + binary::IDLE_GAP)
+    }
+
+    /// Stamp parsed records with their receive time and add them to the
+    /// buffer. Returns the alarms raised by them.
+    fn add_records(
+        &mut self,
+        records: Vec<Record>,
+        received: Instant,
+        settings: &AppSettings,
+    ) -> Vec<AlarmEvent> {
+        let time = received
+            .saturating_duration_since(self.origin)
+            .as_secs_f64();
+
+        let mut alarms = Vec::new();
+        for record in records {
+            match record {
+                Record::Columns(values) => self.data.push_record(&values, time),
+                Record::Named(values) => self.data.push_named(&values, time),
+            }
+            self.evaluate_derived(self.data.len() - 1);
+            alarms.extend(self.alarms.check(&settings.chart_thresholds, &self.data));
+        }
+
+        self.alarm_count += alarms.len() as u64;
+        if alarms.iter().any(|alarm| alarm.threshold.pause) {
+            self.pause_requested = true;
+        }
+        alarms
+    }
+
+    /// Rebuild the parser when its settings changed. Invalid settings keep
+    /// the previous parser; the settings panel shows the error.
+    fn update_parser(&mut self, settings: &AppSettings) {
+        let config = ParserConfig::from_settings(settings);
+        if config == self.parser_config {
+            return;
+        }
+        if let Ok(parser) = ChartParser::new(&config) {
+            self.parser = parser;
+        }
+        self.parser_config = config;
+    }
+
+    /// Recompile the derived series when their definitions changed and
+    /// compute them over the whole buffer. Invalid expressions are left out;
+    /// the editor shows the error.
+    fn update_derived(&mut self, settings: &AppSettings) {
+        if settings.chart_derived == self.derived_config {
+            return;
+        }
+        self.derived_config = settings.chart_derived.clone();
+        self.derived = self
+            .derived_config
+            .iter()
+            .filter(|derived| !derived.name.trim().is_empty())
+            .filter_map(|derived| {
+                let expression = Expression::parse(&derived.expression).ok()?;
+                Some((derived.name.trim().to_string(), expression))
+            })
+            .collect();
+
+        self.data.remove_derived();
+        for index in 0..self.data.len() {
+            self.evaluate_derived(index);
+        }
+    }
+
+    /// Compute the derived series for the record at `index`.
+    fn evaluate_derived(&mut self, index: usize) {
+        let time = self.data.times[index];
+        for (name, expression) in &mut self.derived {
+            let data = &self.data;
+            let lookup = |series_name: &str| {
+                data.series
+                    .iter()
+                    .find(|series| series.name == series_name)
+                    .map(|series| series.values[index])
+            };
+            let value = expression.eval(&lookup, time);
+            self.data.set_derived(name, index, value);
+        }
+    }
+
+    /// Keep a fixed number of records, or the selected time window when the
+    /// X axis shows time. While paused, the records from the start of the
+    /// frozen view on are kept too.
+    fn apply_limits(&mut self, settings: &AppSettings) {
+        let keep_from = match self.view {
+            ChartView {
+                paused: true,
+                x_range: Some((x_min, _)),
+                ..
+            } => Some(self.data.first_index() + self.visible_indices(x_min, x_min).start as u64),
+            _ => None,
+        };
+        match settings.chart_x_axis {
+            ChartXAxis::Samples => {
+                self.data
+                    .set_limits(settings.chart_buffer_size, None, keep_from)
+            }
+            ChartXAxis::Time => self.data.set_limits(
+                MAX_RECORDS,
+                Some(settings.chart_time_window_secs),
+                keep_from,
+            ),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.parser.clear();
+        self.view = ChartView::default();
+        self.origin = Instant::now();
+        self.stats_start = 0;
+        self.spectrum_cache = None;
+        self.trigger.reset();
+        self.alarms.clear();
+        self.alarm_count = 0;
+        self.pause_requested = false;
+        // Start the moving averages and derivatives afresh
+        self.derived_config.clear();
+        self.derived.clear();
+    }
+
+    /// X coordinate of the record at `index` in the buffer.
+    fn x_at(&self, index: usize) -> f64 {
+        match self.x_axis {
+            ChartXAxis::Samples => (self.data.first_index() + index as u64) as f64,
+            ChartXAxis::Time => self.data.times[index],
         }
     }
 
-    pub fn process_rx(&mut self, message: Vec<u8>) {
-        // Convert bytes to string and add to buffer
-        let message_str = String::from_utf8_lossy(&message);
-        self.stream_buffer.push_str(&message_str);
+    fn x_formatter(&self) -> fn(f64) -> String {
+        match self.x_axis {
+            ChartXAxis::Samples => |x| format!("{x:.0}"),
+            ChartXAxis::Time => |x| format!("{x:.1} s"),
+        }
+    }
 
-        // Process complete values (those followed by commas)
-        while let Some(comma_pos) = self.stream_buffer.find(',') {
-            // Extract the complete value (everything up to the comma)
-            let complete_value = self.stream_buffer[..comma_pos].trim().to_string();
+    /// Buffer indices of the records between `x_min` and `x_max`.
+    fn in_view_indices(&self, x_min: f64, x_max: f64) -> Range<usize> {
+        let (start, end) = match self.x_axis {
+            ChartXAxis::Samples => {
+                let first = self.data.first_index() as f64;
+                (
+                    (x_min - first).ceil().max(0.0) as usize,
+                    ((x_max - first).floor() + 1.0).max(0.0) as usize,
+                )
+            }
+            ChartXAxis::Time => (
+                self.data.times.partition_point(|&t| t < x_min),
+                self.data.times.partition_point(|&t| t <= x_max),
+            ),
+        };
+        let end = end.min(self.data.len());
+        start.min(end)..end
+    }
 
-            // Remove the processed part from buffer (including the comma)
-            self.stream_buffer = self.stream_buffer[comma_pos + 1..].to_string();
+    /// Buffer indices of the records between `x_min` and `x_max`, plus one
+    /// on each side so lines reach the edges of the plot.
+    fn visible_indices(&self, x_min: f64, x_max: f64) -> Range<usize> {
+        let in_view = self.in_view_indices(x_min, x_max);
+        let end = (in_view.end + 1).min(self.data.len());
+        in_view.start.saturating_sub(1).min(end)..end
+    }
 
-            // Try to parse the complete value
-            if !complete_value.is_empty() {
-                if let Ok(sample) = complete_value.parse::<f32>() {
-                    self.samples.push_back(sample);
+    /// Buffer index of the record closest to `x`.
+    fn nearest_index(&self, x: f64) -> Option<usize> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let last = self.data.len() - 1;
+        let index = match self.x_axis {
+            ChartXAxis::Samples => (x - self.data.first_index() as f64)
+                .round()
+                .clamp(0.0, last as f64) as usize,
+            ChartXAxis::Time => {
+                let after = self.data.times.partition_point(|&t| t < x).min(last);
+                if after > 0 && x - self.data.times[after - 1] < self.data.times[after] - x {
+                    after - 1
+                } else {
+                    after
                 }
             }
+        };
+        Some(index)
+    }
+
+    /// X range shown when following the latest data.
+    fn latest_x_range(&self, settings: &AppSettings) -> (f64, f64) {
+        match self.x_axis {
+            ChartXAxis::Samples => {
+                let first = self.data.first_index() as f64;
+                let end = first + self.data.len() as f64;
+                let start = (end - settings.chart_window_size as f64).max(first);
+                (start, end.max(start + 10.0))
+            }
+            ChartXAxis::Time => {
+                let end = self.origin.elapsed().as_secs_f64();
+                (end - settings.chart_time_window_secs, end)
+            }
+        }
+    }
+
+    /// Statistics of one series over the records at `indices`.
+    fn series_stats(&self, series: usize, indices: Range<usize>) -> SeriesStats {
+        let values = &self.data.series[series].values;
+        SeriesStats::compute(indices.map(|i| (self.data.times[i], values[i])))
+    }
+
+    /// Smallest and largest value of the visible series selected by
+    /// `include` between `x_min` and `x_max`, with some padding.
+    fn value_range(
+        &self,
+        x_min: f64,
+        x_max: f64,
+        include: impl Fn(usize) -> bool,
+    ) -> Option<(f64, f64)> {
+        let indices = self.visible_indices(x_min, x_max);
+        let (min_y, max_y) = (0..self.data.series.len())
+            .filter(|&series| self.data.series[series].visible && include(series))
+            .map(|series| self.series_stats(series, indices.clone()))
+            .filter(|stats| stats.count > 0)
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), stats| {
+                (min.min(stats.min), max.max(stats.max))
+            });
+        if min_y > max_y {
+            return None;
+        }
+
+        let y_range = max_y - min_y;
+        let padding = if y_range > 0.0 { y_range * 0.1 } else { 1.0 };
+        Some((min_y - padding, max_y + padding))
+    }
+
+    fn is_on_right_axis(&self, series: usize) -> bool {
+        self.layout == ChartLayout::Overlay && self.data.series[series].axis == YAxis::Right
+    }
+
+    /// Range of the right Y axis, if a visible series uses it.
+    fn right_range(&self, x_min: f64, x_max: f64) -> Option<(f64, f64)> {
+        self.value_range(x_min, x_max, |series| self.is_on_right_axis(series))
+            .map(|autoscaled| self.view.y2_range.unwrap_or(autoscaled))
+    }
+
+    /// Freeze the view at `current`. The stacked plots keep scaling to the
+    /// frozen X range, which the kept records no longer change.
+    fn pause(&mut self, current: ((f64, f64), (f64, f64))) {
+        self.view.x_range = Some(current.0);
+        if self.layout == ChartLayout::Overlay {
+            self.view.y_range = Some(current.1);
+        }
+        self.view.paused = true;
+    }
+
+    /// One series between the buffer `indices`, split at gaps. Values are
+    /// rescaled from `right_range` to `y_range` for the right axis.
+    fn series_snapshot(
+        &self,
+        series: usize,
+        indices: Range<usize>,
+        rescaled: Option<((f64, f64), (f64, f64))>,
+    ) -> SeriesSnapshot {
+        let series = &self.data.series[series];
+        let mut segments = vec![Vec::new()];
+        for i in indices {
+            let value = series.values[i];
+            if value.is_finite() {
+                let y = rescaled.map_or(value, |(from, to)| rescale(value, from, to));
+                segments.last_mut().unwrap().push((self.x_at(i), y));
+            } else if !segments.last().unwrap().is_empty() {
+                segments.push(Vec::new());
+            }
+        }
+        segments.retain(|segment| !segment.is_empty());
+        SeriesSnapshot {
+            name: series.name.clone(),
+            color: series.color,
+            segments,
         }
+    }
 
-        // Prevent buffer from growing indefinitely
-        // Keep only the last incomplete value
-        if self.stream_buffer.len() > 50 {
-            // If buffer gets too long without a comma, it's probably garbage
-            // Keep only the last 20 characters in case there's a valid number at the end
-            let keep_from = self.stream_buffer.len().saturating_sub(20);
-            self.stream_buffer = self.stream_buffer[keep_from..].to_string();
+    /// The visible series between the current axis ranges, with right axis
+    /// series rescaled to the left axis.
+    fn snapshot(&self, current: ((f64, f64), (f64, f64))) -> PlotSnapshot {
+        let (x_min, x_max) = current.0;
+        let indices = self.visible_indices(x_min, x_max);
+        let right_range = self.right_range(x_min, x_max);
+        let series = (0..self.data.series.len())
+            .filter(|&series| self.data.series[series].visible)
+            .map(|series| {
+                let rescaled = right_range
+                    .filter(|_| self.is_on_right_axis(series))
+                    .map(|from| (from, current.1));
+                self.series_snapshot(series, indices.clone(), rescaled)
+            })
+            .collect();
+
+        PlotSnapshot {
+            x_axis: self.x_axis,
+            x_range: current.0,
+            y_range: current.1,
+            series,
         }
+    }
+
+    fn export_menu(&mut self, ui: &mut egui::Ui, current: ((f64, f64), (f64, f64))) {
+        ui.menu_button("Export", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("File name:");
+                ui.text_edit_singleline(&mut self.export_path);
+            });
+            ui.horizontal(|ui| {
+                if ui
+                    .button("CSV")
+                    .on_hover_text("All buffered records with timestamps")
+                    .clicked()
+                {
+                    let path = with_extension(&self.export_path, "csv");
+                    self.export_status = Some(match export_csv(&path, &self.data) {
+                        Ok(()) => format!("Saved {path}"),
+                        Err(e) => format!("Export failed: {e}"),
+                    });
+                }
+                if ui
+                    .button("PNG")
+                    .on_hover_text("The plot as shown")
+                    .clicked()
+                {
+                    self.pending_png = Some(with_extension(&self.export_path, "png"));
+                    ui.ctx()
+                        .send_viewport_cmd(egui::ViewportCommand::Screenshot(Default::default()));
+                    ui.close_menu();
+                }
+                if ui
+                    .button("SVG")
+                    .on_hover_text("The visible part of the plot as vector graphics")
+                    .clicked()
+                {
+                    let path = with_extension(&self.export_path, "svg");
+                    self.export_status = Some(match export_svg(&path, &self.snapshot(current)) {
+                        Ok(()) => format!("Saved {path}"),
+                        Err(e) => format!("Export failed: {e}"),
+                    });
+                }
+            });
+            if let Some(status) = &self.export_status {
+                ui.label(status);
+            }
+        });
+    }
 
-        // Limit the samples size to prevent memory growth
-        while self.samples.len() > 1000 {
-            self.samples.pop_front();
+    /// Crop the requested screenshot to the plot and write the PNG file.
+    fn save_pending_png(&mut self, ctx: &egui::Context) {
+        if self.pending_png.is_none() {
+            return;
         }
+        let screenshot = ctx.input(|i| {
+            i.events.iter().find_map(|event| match event {
+                egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        let (Some(image), Some(rect)) = (screenshot, self.chart_rect) else {
+            return;
+        };
+
+        let path = self.pending_png.take().unwrap_or_default();
+        let plot = image.region(&rect, Some(ctx.pixels_per_point()));
+        self.export_status = Some(match export_png(&path, &plot) {
+            Ok(()) => format!("Saved {path}"),
+            Err(e) => format!("Export failed: {e}"),
+        });
     }
 
-    pub fn show(&mut self, ui: &mut egui::Ui, available_size: Vec2, _autoscroll: bool) {
-        // Reserve space for axes labels
-        let margin_left = 60.0;
-        let margin_bottom = 30.0;
-        let plot_size = Vec2::new(
-            available_size.x - margin_left,
-            (available_size.y * 0.85) - margin_bottom,
+    fn legend(&mut self, ui: &mut egui::Ui, settings: &mut AppSettings) {
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Format:");
+            let previous_mode = settings.chart_parse_mode;
+            egui::ComboBox::from_id_salt("chart_parse_mode_combo")
+                .selected_text(settings.chart_parse_mode.to_string())
+                .show_ui(ui, |ui| {
+                    for mode in ChartParseMode::ALL {
+                        ui.selectable_value(&mut settings.chart_parse_mode, mode, mode.to_string());
+                    }
+                });
+            // Series of one format mean nothing in the other
+            if settings.chart_parse_mode != previous_mode {
+                self.clear();
+            }
+            if settings.chart_parse_mode == ChartParseMode::Binary
+                && ui.button("Frame format...").clicked()
+            {
+                self.binary_format_panel.open = !self.binary_format_panel.open;
+            }
+            ui.separator();
+
+            let axis_toggle = settings.chart_display == ChartDisplay::Time
+                && settings.chart_layout == ChartLayout::Overlay;
+            for series in &mut self.data.series {
+                ui.checkbox(&mut series.visible, "");
+                if axis_toggle {
+                    let (text, other) = match series.axis {
+                        YAxis::Left => ("L", YAxis::Right),
+                        YAxis::Right => ("R", YAxis::Left),
+                    };
+                    if ui
+                        .small_button(text)
+                        .on_hover_text("Y axis: left or right")
+                        .clicked()
+                    {
+                        series.axis = other;
+                    }
+                }
+                egui::color_picker::color_edit_button_srgba(
+                    ui,
+                    &mut series.color,
+                    egui::color_picker::Alpha::Opaque,
+                );
+                match series.latest() {
+                    Some(latest) => ui.label(format!("{}: {latest:.3}", series.name)),
+                    None => ui.label(&series.name),
+                };
+                ui.add_space(8.0);
+            }
+            if self.parser.ignored > 0 {
+                ui.weak(format!("{} records ignored", self.parser.ignored))
+                    .on_hover_text("Received records that were not chart data, e.g. log messages");
+            }
+            if self.parser.skipped_bytes > 0 {
+                ui.weak(format!("{} bytes skipped", self.parser.skipped_bytes))
+                    .on_hover_text("Received bytes outside of a binary frame");
+            }
+            if self.alarm_count > 0 {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("⚠ {} alarms", self.alarm_count),
+                )
+                .on_hover_text("Threshold crossings since the chart was cleared");
+            }
+            if ui
+                .button("ƒ Derived...")
+                .on_hover_text("Series computed from other series")
+                .clicked()
+            {
+                self.derived_panel.open = !self.derived_panel.open;
+            }
+            if ui.button("Clear").clicked() {
+                self.clear();
+            }
+        });
+
+        self.binary_format_panel
+            .show(ui.ctx(), &mut settings.chart_binary_format);
+        self.derived_panel
+            .show(ui.ctx(), &mut settings.chart_derived);
+        let series_names: Vec<String> = self
+            .data
+            .series
+            .iter()
+            .map(|series| series.name.clone())
+            .collect();
+        self.threshold_panel.show(
+            ui.ctx(),
+            &mut settings.chart_thresholds,
+            &series_names,
+            &settings.macros,
         );
+    }
 
+    fn view_controls(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings: &mut AppSettings,
+        current: ((f64, f64), (f64, f64)),
+    ) {
         ui.horizontal(|ui| {
-            // Y-axis labels area
-            ui.allocate_ui_with_layout(
-                Vec2::new(margin_left - 10.0, plot_size.y),
-                egui::Layout::top_down(egui::Align::RIGHT),
-                |ui| {
-                    if !self.samples.is_empty() {
-                        let min_y = self.samples.iter().cloned().fold(f32::INFINITY, f32::min);
-                        let max_y = self
-                            .samples
-                            .iter()
-                            .cloned()
-                            .fold(f32::NEG_INFINITY, f32::max);
-                        let y_range = max_y - min_y;
-                        let padding = if y_range > 0.0 { y_range * 0.1 } else { 1.0 };
-                        let padded_min = min_y - padding;
-                        let padded_max = max_y + padding;
-
-                        // Draw Y-axis labels
-                        for i in 0..=5 {
-                            let y_val =
-                                padded_min + (padded_max - padded_min) * (1.0 - i as f32 / 5.0);
-                            let y_pos = (plot_size.y / 5.0) * i as f32;
-
-                            ui.allocate_new_ui(
-                                egui::UiBuilder::new().max_rect(Rect::from_min_size(
-                                    ui.min_rect().min + egui::vec2(0.0, y_pos - 8.0),
-                                    Vec2::new(margin_left - 15.0, 16.0),
-                                )),
-                                |ui| {
-                                    ui.with_layout(
-                                        egui::Layout::right_to_left(egui::Align::Center),
-                                        |ui| {
-                                            ui.label(format!("{y_val:.1}"));
-                                        },
-                                    );
-                                },
+            display_combo(ui, settings);
+            if self.view.paused {
+                if ui.button("▶ Resume").clicked() {
+                    self.view = ChartView::default();
+                }
+            } else if ui
+                .button("⏸ Pause")
+                .on_hover_text("Freeze the view. Data keeps being recorded.")
+                .clicked()
+            {
+                self.pause(current);
+            }
+
+            let mut follow = self.view.x_range.is_none();
+            if ui.checkbox(&mut follow, "Follow latest").changed() {
+                self.view = if follow {
+                    ChartView::default()
+                } else {
+                    ChartView {
+                        x_range: Some(current.0),
+                        ..ChartView::default()
+                    }
+                };
+            }
+
+            ui.label("Layout:");
+            egui::ComboBox::from_id_salt("chart_layout_combo")
+                .width(80.0)
+                .selected_text(settings.chart_layout.to_string())
+                .show_ui(ui, |ui| {
+                    for layout in ChartLayout::ALL {
+                        ui.selectable_value(&mut settings.chart_layout, layout, layout.to_string())
+                            .on_hover_text(match layout {
+                                ChartLayout::Overlay => {
+                                    "One plot; series can use a left or a right Y axis"
+                                }
+                                ChartLayout::Stacked => "One plot per series, sharing the X axis",
+                            });
+                    }
+                });
+
+            ui.label("X axis:");
+            let previous_axis = settings.chart_x_axis;
+            egui::ComboBox::from_id_salt("chart_x_axis_combo")
+                .selected_text(settings.chart_x_axis.to_string())
+                .show_ui(ui, |ui| {
+                    for axis in ChartXAxis::ALL {
+                        ui.selectable_value(&mut settings.chart_x_axis, axis, axis.to_string());
+                    }
+                });
+            if settings.chart_x_axis != previous_axis {
+                self.view = ChartView::default();
+                self.x_axis = settings.chart_x_axis;
+                self.apply_limits(settings);
+            }
+
+            ui.label("Window:");
+            match settings.chart_x_axis {
+                ChartXAxis::Samples => {
+                    ui.add(
+                        egui::DragValue::new(&mut settings.chart_window_size)
+                            .range(10..=settings.chart_buffer_size.max(10))
+                            .suffix(" samples"),
+                    );
+                }
+                ChartXAxis::Time => {
+                    let selected = TIME_WINDOWS
+                        .iter()
+                        .find(|(secs, _)| *secs == settings.chart_time_window_secs)
+                        .map_or_else(
+                            || format!("{} s", settings.chart_time_window_secs),
+                            |(_, label)| label.to_string(),
+                        );
+                    egui::ComboBox::from_id_salt("chart_time_window_combo")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            for (secs, label) in TIME_WINDOWS {
+                                if ui
+                                    .selectable_value(
+                                        &mut settings.chart_time_window_secs,
+                                        secs,
+                                        label,
+                                    )
+                                    .changed()
+                                {
+                                    self.apply_limits(settings);
+                                }
+                            }
+                        });
+                }
+            }
+
+            if ui.button("Reset view").clicked() {
+                self.view = ChartView::default();
+            }
+            if ui
+                .selectable_label(self.show_stats, "Statistics")
+                .clicked()
+            {
+                self.show_stats = !self.show_stats;
+            }
+            if ui
+                .selectable_label(self.threshold_panel.open, "Thresholds")
+                .on_hover_text("Limit lines and alarms")
+                .clicked()
+            {
+                self.threshold_panel.open = !self.threshold_panel.open;
+            }
+            self.export_menu(ui, current);
+            ui.label("ℹ").on_hover_text(
+                "Drag to pan\nScroll to zoom the X axis\nCtrl + scroll to zoom the Y axis\nDouble-click to reset",
+            );
+        });
+    }
+
+    /// Window with the statistics of every series, over the buffer since the
+    /// last reset and over the visible window.
+    fn stats_window(&mut self, ctx: &egui::Context, visible: Range<usize>) {
+        if !self.show_stats {
+            return;
+        }
+
+        let first = self.data.first_index();
+        let since_reset =
+            (self.stats_start.saturating_sub(first) as usize).min(self.data.len())..self.data.len();
+
+        let mut open = self.show_stats;
+        egui::Window::new("Chart statistics")
+            .open(&mut open)
+            .default_width(650.0)
+            .show(ctx, |ui| {
+                egui::Grid::new("chart_stats_grid")
+                    .striped(true)
+                    .num_columns(9)
+                    .show(ui, |ui| {
+                        for header in [
+                            "Series", "Range", "Count", "Min", "Max", "Mean", "Std dev", "RMS",
+                            "Rate",
+                        ] {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+
+                        for (index, series) in self.data.series.iter().enumerate() {
+                            for (label, indices) in [
+                                ("Buffer", since_reset.clone()),
+                                ("Visible", visible.clone()),
+                            ] {
+                                let stats = self.series_stats(index, indices);
+                                ui.colored_label(series.color, &series.name);
+                                ui.label(label);
+                                ui.label(stats.count.to_string());
+                                if stats.count > 0 {
+                                    for value in
+                                        [stats.min, stats.max, stats.mean, stats.std_dev, stats.rms]
+                                    {
+                                        ui.label(format!("{value:.4}"));
+                                    }
+                                } else {
+                                    for _ in 0..5 {
+                                        ui.label("-");
+                                    }
+                                }
+                                match stats.sample_rate {
+                                    Some(rate) => ui.label(format!("{rate:.1} Hz")),
+                                    None => ui.label("-"),
+                                };
+                                ui.end_row();
+                            }
+                        }
+                    });
+
+                if ui
+                    .button("Reset")
+                    .on_hover_text("Restart the buffer statistics from the next sample")
+                    .clicked()
+                {
+                    self.stats_start = first + self.data.len() as u64;
+                }
+            });
+        self.show_stats = open;
+    }
+
+    /// Apply drag, scroll and double-click on the plot to the view. The Y
+    /// axes of `target` are moved.
+    fn handle_interaction(
+        &mut self,
+        ui: &egui::Ui,
+        response: &egui::Response,
+        to_screen: &emath::RectTransform,
+        axes: &PlotAxes,
+        target: YTarget,
+    ) {
+        if response.double_clicked() {
+            self.view = ChartView::default();
+            return;
+        }
+
+        let ((mut x_min, mut x_max), (y_min, y_max)) = (axes.x_range, axes.y_range);
+        let rect = response.rect;
+        let x_per_pixel = (x_max - x_min) / rect.width() as f64;
+        // Y pan and zoom as fractions of the axis height, so that both axes
+        // of the overlay plot move alike
+        let mut y_shift = 0.0;
+        let mut y_zoom = 1.0;
+        let mut y_anchor = 0.5;
+        let mut changed = false;
+
+        if response.dragged() {
+            let delta = response.drag_delta();
+            x_min -= delta.x as f64 * x_per_pixel;
+            x_max -= delta.x as f64 * x_per_pixel;
+            y_shift = delta.y as f64 / rect.height() as f64;
+            changed = delta != Vec2::ZERO;
+        }
+
+        if let Some(pointer) = response.hover_pos() {
+            let (scroll, zoom) = ui.input(|i| (i.smooth_scroll_delta.y, i.zoom_delta()));
+            let anchor = to_screen.inverse() * pointer;
+            if scroll != 0.0 {
+                let factor = (-scroll as f64 * 0.003).exp();
+                let x = anchor.x as f64;
+                x_min = x - (x - x_min) * factor;
+                x_max = x + (x_max - x) * factor;
+                changed = true;
+            }
+            if zoom != 1.0 {
+                y_zoom = 1.0 / zoom as f64;
+                y_anchor = (anchor.y as f64 - y_min) / (y_max - y_min);
+                changed = true;
+            }
+        }
+
+        if !changed || x_max <= x_min {
+            return;
+        }
+        let adjust = |(min, max): (f64, f64)| {
+            let span = max - min;
+            let anchor = min + y_anchor * span;
+            let shift = y_shift * span;
+            (
+                anchor - (anchor - min) * y_zoom + shift,
+                anchor + (max - anchor) * y_zoom + shift,
+            )
+        };
+        self.view.x_range = Some((x_min, x_max));
+        match target {
+            YTarget::Overlay => {
+                self.view.y_range = Some(adjust(axes.y_range));
+                if let Some(right) = axes.y2_range {
+                    self.view.y2_range = Some(adjust(right));
+                }
+            }
+            YTarget::Stacked(series) => {
+                self.view
+                    .stacked_y_ranges
+                    .insert(series, adjust(axes.y_range));
+            }
+        }
+    }
+
+    /// Vertical cursor at the record under the pointer and a tooltip with
+    /// its values.
+    fn hover_readout(
+        &self,
+        response: &egui::Response,
+        to_screen: &emath::RectTransform,
+        shapes: &mut Vec<epaint::Shape>,
+    ) {
+        let Some(pointer) = response.hover_pos() else {
+            return;
+        };
+        if response.dragged() {
+            return;
+        }
+
+        let x = (to_screen.inverse() * pointer).x as f64;
+        let Some(index) = self.nearest_index(x) else {
+            return;
+        };
+        let cursor_x = to_screen
+            .transform_pos(pos2(self.x_at(index) as f32, 0.0))
+            .x;
+        shapes.push(epaint::Shape::line_segment(
+            [
+                pos2(cursor_x, response.rect.top()),
+                pos2(cursor_x, response.rect.bottom()),
+            ],
+            (1.0, Color32::GRAY.gamma_multiply(0.6)),
+        ));
+
+        response.clone().on_hover_ui_at_pointer(|ui| {
+            match self.x_axis {
+                ChartXAxis::Samples => ui.label(format!("Sample {}", self.x_at(index))),
+                ChartXAxis::Time => ui.label(format!("t = {:.3} s", self.x_at(index))),
+            };
+            for series in self.data.series.iter().filter(|series| series.visible) {
+                let value = series.values[index];
+                let text = if value.is_finite() {
+                    format!("{}: {value:.3}", series.name)
+                } else {
+                    format!("{}: -", series.name)
+                };
+                ui.colored_label(series.color, text);
+            }
+        });
+    }
+
+    /// The latest `count` values of a series with their receive times,
+    /// skipping gaps. Fewer are returned while the buffer holds less.
+    fn latest_samples(&self, series: usize, count: usize) -> Vec<(f64, f64)> {
+        let Some(series) = self.data.series.get(series) else {
+            return Vec::new();
+        };
+        let mut samples: Vec<(f64, f64)> = self
+            .data
+            .times
+            .iter()
+            .zip(&series.values)
+            .rev()
+            .filter(|(_, value)| value.is_finite())
+            .take(count)
+            .map(|(&time, &value)| (time, value))
+            .collect();
+        samples.reverse();
+        samples
+    }
+
+    fn spectrum_controls(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings: &mut AppSettings,
+        collected: usize,
+        rate: Option<f64>,
+        bins: &[(f64, f64)],
+    ) {
+        ui.horizontal(|ui| {
+            display_combo(ui, settings);
+
+            ui.label("Series:");
+            let selected = self
+                .data
+                .series
+                .get(self.spectrum_series)
+                .map_or_else(String::new, |series| series.name.clone());
+            egui::ComboBox::from_id_salt("chart_spectrum_series_combo")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (index, series) in self.data.series.iter().enumerate() {
+                        ui.selectable_value(&mut self.spectrum_series, index, &series.name);
+                    }
+                });
+
+            ui.label("FFT size:");
+            egui::ComboBox::from_id_salt("chart_fft_size_combo")
+                .selected_text(settings.chart_fft_size.to_string())
+                .show_ui(ui, |ui| {
+                    for size in FFT_SIZES {
+                        ui.selectable_value(&mut settings.chart_fft_size, size, size.to_string());
+                    }
+                });
+
+            ui.label("Window:");
+            egui::ComboBox::from_id_salt("chart_fft_window_combo")
+                .selected_text(settings.chart_fft_window.to_string())
+                .show_ui(ui, |ui| {
+                    for window in WindowFunction::ALL {
+                        ui.selectable_value(
+                            &mut settings.chart_fft_window,
+                            window,
+                            window.to_string(),
+                        );
+                    }
+                });
+            ui.checkbox(&mut settings.chart_fft_remove_dc, "Remove DC")
+                .on_hover_text("Subtract the mean so an offset does not hide low frequencies");
+            ui.separator();
+
+            if collected < settings.chart_fft_size {
+                ui.label(format!(
+                    "Waiting for samples: {collected} / {}",
+                    settings.chart_fft_size
+                ));
+                if settings.chart_x_axis == ChartXAxis::Samples
+                    && settings.chart_fft_size > settings.chart_buffer_size
+                {
+                    ui.weak("(increase the chart buffer size in the settings)");
+                }
+                return;
+            }
+
+            match rate {
+                Some(rate) => ui.label(format!("Sample rate: {rate:.1} Hz")),
+                None => ui
+                    .label("Sample rate unknown")
+                    .on_hover_text("Frequencies are shown in cycles per sample"),
+            };
+            // Strongest component apart from DC
+            if let Some(&(frequency, db)) = bins.iter().skip(1).max_by(|a, b| a.1.total_cmp(&b.1)) {
+                ui.label(format!("Peak: {frequency:.2} at {db:.1} dB"));
+            }
+        });
+    }
+
+    /// Magnitude in dB over frequency of the latest samples of one series,
+    /// using the sample rate measured from their receive times.
+    fn show_spectrum(
+        &mut self,
+        ui: &mut egui::Ui,
+        available_size: Vec2,
+        settings: &mut AppSettings,
+    ) {
+        if !FFT_SIZES.contains(&settings.chart_fft_size) {
+            settings.chart_fft_size = AppSettings::default().chart_fft_size;
+        }
+        if self.spectrum_series >= self.data.series.len() {
+            self.spectrum_series = 0;
+        }
+
+        let key = (
+            self.data.first_index(),
+            self.data.len(),
+            self.data.series.len(),
+            self.spectrum_series,
+            settings.chart_fft_size,
+            settings.chart_fft_window,
+            settings.chart_fft_remove_dc,
+        );
+        if self
+            .spectrum_cache
+            .as_ref()
+            .map_or(true, |cache| cache.key != key)
+        {
+            let samples = self.latest_samples(self.spectrum_series, settings.chart_fft_size);
+            let rate = SeriesStats::compute(samples.iter().copied()).sample_rate;
+            let bins = if samples.len() == settings.chart_fft_size {
+                let mut values: Vec<f64> = samples.iter().map(|&(_, value)| value).collect();
+                if settings.chart_fft_remove_dc {
+                    let mean = values.iter().sum::<f64>() / values.len() as f64;
+                    values.iter_mut().for_each(|value| *value -= mean);
+                }
+                spectrum(&values, settings.chart_fft_window, rate.unwrap_or(1.0))
+            } else {
+                Vec::new()
+            };
+            self.spectrum_cache = Some(SpectrumCache {
+                key,
+                collected: samples.len(),
+                rate,
+                bins,
+            });
+        }
+        // Taken out while the controls borrow the panel, put back below
+        let Some(cache) = self.spectrum_cache.take() else {
+            return;
+        };
+        let (rate, bins) = (cache.rate, &cache.bins);
+
+        self.spectrum_controls(ui, settings, cache.collected, rate, bins);
+
+        let (min_db, max_db) = bins.iter().fold(
+            (f64::INFINITY, f64::NEG_INFINITY),
+            |(min, max), &(_, db)| (min.min(db), max.max(db)),
+        );
+        let labeled = !bins.is_empty();
+        let padding = ((max_db - min_db) * 0.1).max(1.0);
+        let axes = PlotAxes {
+            x_range: bins
+                .last()
+                .map_or((0.0, 1.0), |&(nyquist, _)| (0.0, nyquist)),
+            y_range: if labeled {
+                (min_db - padding, max_db + padding)
+            } else {
+                (-1.0, 1.0)
+            },
+            labeled,
+            y2_range: None,
+            x_labels: true,
+            format_x: if rate.is_some() {
+                |x| format!("{x:.1} Hz")
+            } else {
+                |x| format!("{x:.3}")
+            },
+        };
+        let color = self
+            .data
+            .series
+            .get(self.spectrum_series)
+            .map_or(Color32::GRAY, |series| series.color);
+
+        let chart_rect = show_plot(
+            ui,
+            plot_size(available_size),
+            &axes,
+            |_, response, to_screen, shapes| {
+                if bins.len() < 2 {
+                    return;
+                }
+                let points: Vec<Pos2> = bins
+                    .iter()
+                    .map(|&(frequency, db)| to_screen * pos2(frequency as f32, db as f32))
+                    .collect();
+                shapes.push(epaint::Shape::line(points, PathStroke::new(1.5, color)));
+
+                // Readout of the bin under the pointer
+                let Some(pointer) = response.hover_pos() else {
+                    return;
+                };
+                let frequency = (to_screen.inverse() * pointer).x as f64;
+                let bin_width = bins[1].0;
+                let index = ((frequency / bin_width).round().max(0.0) as usize).min(bins.len() - 1);
+                let (frequency, db) = bins[index];
+                let cursor_x = to_screen.transform_pos(pos2(frequency as f32, 0.0)).x;
+                shapes.push(epaint::Shape::line_segment(
+                    [
+                        pos2(cursor_x, response.rect.top()),
+                        pos2(cursor_x, response.rect.bottom()),
+                    ],
+                    (1.0, Color32::GRAY.gamma_multiply(0.6)),
+                ));
+                response.clone().on_hover_ui_at_pointer(|ui| {
+                    ui.label((axes.format_x)(frequency));
+                    ui.colored_label(color, format!("{db:.1} dB"));
+                });
+            },
+        );
+        self.chart_rect = Some(chart_rect);
+        self.spectrum_cache = Some(cache);
+    }
+
+    fn trigger_controls(&mut self, ui: &mut egui::Ui, settings: &mut AppSettings) {
+        ui.horizontal(|ui| {
+            display_combo(ui, settings);
+            let trigger = &mut settings.chart_trigger;
+
+            ui.label("Trigger on:");
+            let selected = self
+                .data
+                .series
+                .get(self.trigger_series)
+                .map_or_else(String::new, |series| series.name.clone());
+            egui::ComboBox::from_id_salt("chart_trigger_series_combo")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (index, series) in self.data.series.iter().enumerate() {
+                        ui.selectable_value(&mut self.trigger_series, index, &series.name);
+                    }
+                });
+
+            egui::ComboBox::from_id_salt("chart_trigger_edge_combo")
+                .width(70.0)
+                .selected_text(trigger.edge.to_string())
+                .show_ui(ui, |ui| {
+                    for edge in TriggerEdge::ALL {
+                        ui.selectable_value(&mut trigger.edge, edge, edge.to_string());
+                    }
+                });
+
+            ui.label("Level:");
+            ui.add(egui::DragValue::new(&mut trigger.level).speed(0.1));
+
+            ui.label("Length:");
+            ui.add(
+                egui::DragValue::new(&mut trigger.length)
+                    .range(10..=settings.chart_buffer_size.max(10))
+                    .suffix(" samples"),
+            );
+            ui.label("Pre-trigger:");
+            ui.add(
+                egui::DragValue::new(&mut trigger.pre_trigger)
+                    .range(0..=trigger.length.saturating_sub(1)),
+            );
+
+            ui.label("Mode:");
+            egui::ComboBox::from_id_salt("chart_trigger_mode_combo")
+                .width(70.0)
+                .selected_text(trigger.mode.to_string())
+                .show_ui(ui, |ui| {
+                    for mode in TriggerMode::ALL {
+                        ui.selectable_value(&mut trigger.mode, mode, mode.to_string());
+                    }
+                });
+            if trigger.mode == TriggerMode::Single
+                && ui
+                    .add_enabled(!self.trigger.armed, egui::Button::new("Arm"))
+                    .on_hover_text("Wait for the next trigger")
+                    .clicked()
+            {
+                self.trigger.arm(&self.data);
+            }
+            ui.separator();
+
+            let status = match &self.trigger.capture {
+                _ if trigger.mode == TriggerMode::Single && self.trigger.armed => "Armed",
+                Some(capture) if capture.triggered => "Triggered",
+                Some(_) => "Auto (untriggered)",
+                None => "Waiting for trigger",
+            };
+            ui.label(status);
+        });
+    }
+
+    /// Captures around level crossings of one series, with the trigger point
+    /// at X = 0, so repetitive waveforms stand still.
+    fn show_triggered(
+        &mut self,
+        ui: &mut egui::Ui,
+        available_size: Vec2,
+        settings: &mut AppSettings,
+    ) {
+        if self.trigger_series >= self.data.series.len() {
+            self.trigger_series = 0;
+        }
+        self.trigger
+            .update(&self.data, self.trigger_series, &settings.chart_trigger);
+        self.trigger_controls(ui, settings);
+
+        let level = settings.chart_trigger.level;
+        let (pre_trigger, length) = self.trigger.capture.as_ref().map_or((0, 0), |capture| {
+            (
+                capture.pre_trigger,
+                capture.values.first().map_or(0, Vec::len),
+            )
+        });
+        let x_range = (-(pre_trigger as f64), (length - pre_trigger) as f64);
+        let visible = |index: usize| self.data.series.get(index).is_some_and(|s| s.visible);
+
+        let (min_y, max_y) = self
+            .trigger
+            .capture
+            .iter()
+            .flat_map(|capture| capture.values.iter().enumerate())
+            .filter(|(index, _)| visible(*index))
+            .flat_map(|(_, values)| values.iter().copied())
+            .filter(|value| value.is_finite())
+            .fold((level, level), |(min, max), value| {
+                (min.min(value), max.max(value))
+            });
+        let padding = if max_y > min_y {
+            (max_y - min_y) * 0.1
+        } else {
+            1.0
+        };
+        let axes = PlotAxes {
+            x_range,
+            y_range: (min_y - padding, max_y + padding),
+            labeled: length > 0,
+            y2_range: None,
+            x_labels: true,
+            format_x: |x| format!("{x:.0}"),
+        };
+
+        let chart_rect = show_plot(
+            ui,
+            plot_size(available_size),
+            &axes,
+            |_, response, to_screen, shapes| {
+                let Some(capture) = &self.trigger.capture else {
+                    return;
+                };
+
+                // Trigger level and point
+                let level_color = Color32::from_rgb(255, 160, 0).gamma_multiply(0.7);
+                shapes.extend(epaint::Shape::dashed_line(
+                    &[
+                        to_screen * pos2(x_range.0 as f32, level as f32),
+                        to_screen * pos2(x_range.1 as f32, level as f32),
+                    ],
+                    (1.0, level_color),
+                    6.0,
+                    4.0,
+                ));
+                let trigger_x = to_screen.transform_pos(pos2(0.0, 0.0)).x;
+                shapes.push(epaint::Shape::line_segment(
+                    [
+                        pos2(trigger_x, response.rect.top()),
+                        pos2(trigger_x, response.rect.bottom()),
+                    ],
+                    (1.0, level_color),
+                ));
+
+                let offset = |i: usize| i as f64 - pre_trigger as f64;
+                for (index, values) in capture.values.iter().enumerate() {
+                    if !visible(index) {
+                        continue;
+                    }
+                    let color = self.data.series[index].color;
+                    let mut points = Vec::new();
+                    for (i, &value) in values.iter().enumerate() {
+                        if value.is_finite() {
+                            points.push(to_screen * pos2(offset(i) as f32, value as f32));
+                        } else if !points.is_empty() {
+                            shapes.push(epaint::Shape::line(
+                                std::mem::take(&mut points),
+                                PathStroke::new(2.0, color),
+                            ));
+                        }
+                    }
+                    shapes.push(epaint::Shape::line(points, PathStroke::new(2.0, color)));
+                }
+
+                // Readout of the sample under the pointer
+                let Some(pointer) = response.hover_pos() else {
+                    return;
+                };
+                let x = (to_screen.inverse() * pointer).x as f64 + pre_trigger as f64;
+                let i = (x.round().max(0.0) as usize).min(length.saturating_sub(1));
+                response.clone().on_hover_ui_at_pointer(|ui| {
+                    ui.label(format!("Sample {:+}", offset(i)));
+                    for (index, values) in capture.values.iter().enumerate() {
+                        if visible(index) && values[i].is_finite() {
+                            let series = &self.data.series[index];
+                            ui.colored_label(
+                                series.color,
+                                format!("{}: {:.3}", series.name, values[i]),
                             );
                         }
                     }
-                },
+                });
+            },
+        );
+        self.chart_rect = Some(chart_rect);
+    }
+
+    fn xy_controls(&mut self, ui: &mut egui::Ui, settings: &mut AppSettings) {
+        ui.horizontal(|ui| {
+            display_combo(ui, settings);
+
+            for (label, id, selected) in [
+                ("X:", "chart_xy_x_combo", &mut self.xy_series.0),
+                ("Y:", "chart_xy_y_combo", &mut self.xy_series.1),
+            ] {
+                ui.label(label);
+                let text = self
+                    .data
+                    .series
+                    .get(*selected)
+                    .map_or_else(String::new, |series| series.name.clone());
+                egui::ComboBox::from_id_salt(id)
+                    .selected_text(text)
+                    .show_ui(ui, |ui| {
+                        for (index, series) in self.data.series.iter().enumerate() {
+                            ui.selectable_value(selected, index, &series.name);
+                        }
+                    });
+            }
+
+            ui.label("Trail:");
+            ui.add(
+                egui::DragValue::new(&mut settings.chart_xy_trail)
+                    .range(1..=settings.chart_buffer_size.max(1))
+                    .suffix(" points"),
             );
+            ui.checkbox(&mut settings.chart_xy_fade, "Fade");
+            ui.checkbox(&mut settings.chart_xy_equal_scale, "Equal scale")
+                .on_hover_text("Same units per pixel on both axes, so circles stay round");
+        });
+    }
 
-            // Plot area
-            Frame::canvas(ui.style()).show(ui, |ui| {
-                ui.ctx().request_repaint();
+    /// The latest points of one series against another, older points fading
+    /// out.
+    fn show_xy(&mut self, ui: &mut egui::Ui, available_size: Vec2, settings: &mut AppSettings) {
+        let count = self.data.series.len();
+        if self.xy_series.0 >= count {
+            self.xy_series.0 = 0;
+        }
+        if self.xy_series.1 >= count {
+            self.xy_series.1 = count.saturating_sub(1).min(1);
+        }
+        self.xy_controls(ui, settings);
 
-                let (_id, rect) = ui.allocate_space(plot_size);
+        let points: Vec<(f64, f64)> = match (
+            self.data.series.get(self.xy_series.0),
+            self.data.series.get(self.xy_series.1),
+        ) {
+            (Some(x), Some(y)) => {
+                let mut points: Vec<(f64, f64)> = x
+                    .values
+                    .iter()
+                    .zip(&y.values)
+                    .rev()
+                    .filter(|(x, y)| x.is_finite() && y.is_finite())
+                    .take(settings.chart_xy_trail)
+                    .map(|(&x, &y)| (x, y))
+                    .collect();
+                points.reverse();
+                points
+            }
+            _ => Vec::new(),
+        };
 
-                // Auto-scale based on actual data
-                let (min_val, max_val, x_range) = if self.samples.is_empty() {
-                    (-1.0, 1.0, 0.0..=100.0)
+        let size = plot_size(available_size);
+        let (mut x_range, mut y_range) = points.iter().fold(
+            (
+                (f64::INFINITY, f64::NEG_INFINITY),
+                (f64::INFINITY, f64::NEG_INFINITY),
+            ),
+            |((x_min, x_max), (y_min, y_max)), &(x, y)| {
+                ((x_min.min(x), x_max.max(x)), (y_min.min(y), y_max.max(y)))
+            },
+        );
+        for range in [&mut x_range, &mut y_range] {
+            let padding = if range.1 > range.0 {
+                (range.1 - range.0) * 0.1
+            } else {
+                1.0
+            };
+            *range = (range.0 - padding, range.1 + padding);
+        }
+        if settings.chart_xy_equal_scale && !points.is_empty() {
+            // Widen the axis with fewer units per pixel around its center
+            let x_scale = (x_range.1 - x_range.0) / size.x as f64;
+            let y_scale = (y_range.1 - y_range.0) / size.y as f64;
+            let scale = x_scale.max(y_scale);
+            for (range, pixels) in [(&mut x_range, size.x), (&mut y_range, size.y)] {
+                let center = (range.0 + range.1) / 2.0;
+                let half = scale * pixels as f64 / 2.0;
+                *range = (center - half, center + half);
+            }
+        }
+
+        let axes = PlotAxes {
+            x_range,
+            y_range,
+            labeled: !points.is_empty(),
+            y2_range: None,
+            x_labels: true,
+            format_x: |x| format!("{x:.1}"),
+        };
+        let color = self
+            .data
+            .series
+            .get(self.xy_series.1)
+            .map_or(Color32::GRAY, |series| series.color);
+        let fade = settings.chart_xy_fade;
+
+        let chart_rect = show_plot(ui, size, &axes, |_, response, to_screen, shapes| {
+            let screen: Vec<Pos2> = points
+                .iter()
+                .map(|&(x, y)| to_screen * pos2(x as f32, y as f32))
+                .collect();
+            for (i, segment) in screen.windows(2).enumerate() {
+                let alpha = if fade {
+                    (i + 1) as f32 / screen.len() as f32
                 } else {
-                    let min_y = self.samples.iter().cloned().fold(f32::INFINITY, f32::min);
-                    let max_y = self
-                        .samples
-                        .iter()
-                        .cloned()
-                        .fold(f32::NEG_INFINITY, f32::max);
+                    1.0
+                };
+                shapes.push(epaint::Shape::line_segment(
+                    [segment[0], segment[1]],
+                    (1.5, color.gamma_multiply(alpha)),
+                ));
+            }
+            if let Some(&latest) = screen.last() {
+                shapes.push(epaint::Shape::circle_filled(latest, 4.0, color));
+            }
+
+            if let Some(pointer) = response.hover_pos() {
+                let position = to_screen.inverse() * pointer;
+                response.clone().on_hover_ui_at_pointer(|ui| {
+                    ui.label(format!("x = {:.3}\ny = {:.3}", position.x, position.y));
+                });
+            }
+        });
+        self.chart_rect = Some(chart_rect);
+    }
+
+    fn histogram_controls(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings: &mut AppSettings,
+        data_range: Option<(f64, f64)>,
+        outside: u64,
+    ) {
+        ui.horizontal(|ui| {
+            display_combo(ui, settings);
+
+            ui.label("Series:");
+            let selected = self
+                .data
+                .series
+                .get(self.histogram_series)
+                .map_or_else(String::new, |series| series.name.clone());
+            egui::ComboBox::from_id_salt("chart_histogram_series_combo")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (index, series) in self.data.series.iter().enumerate() {
+                        ui.selectable_value(&mut self.histogram_series, index, &series.name);
+                    }
+                });
+
+            ui.label("Bins:");
+            ui.add(egui::DragValue::new(&mut settings.chart_histogram_bins).range(2..=1000));
+
+            let mut auto = settings.chart_histogram_range.is_none();
+            if ui.checkbox(&mut auto, "Auto range").changed() {
+                settings.chart_histogram_range = if auto {
+                    None
+                } else {
+                    Some(data_range.unwrap_or((0.0, 1.0)))
+                };
+            }
+            if let Some((min, max)) = &mut settings.chart_histogram_range {
+                ui.label("From:");
+                ui.add(egui::DragValue::new(min).speed(0.1));
+                ui.label("to:");
+                ui.add(egui::DragValue::new(max).speed(0.1));
+            }
+
+            if outside > 0 {
+                ui.separator();
+                ui.weak(format!("{outside} values out of range"));
+            }
+        });
+    }
+
+    /// Distribution of the buffered values of one series.
+    fn show_histogram(
+        &mut self,
+        ui: &mut egui::Ui,
+        available_size: Vec2,
+        settings: &mut AppSettings,
+    ) {
+        if self.histogram_series >= self.data.series.len() {
+            self.histogram_series = 0;
+        }
+        let stats = match self.data.series.get(self.histogram_series) {
+            Some(_) => self.series_stats(self.histogram_series, 0..self.data.len()),
+            None => SeriesStats::default(),
+        };
+        let data_range = (stats.count > 0).then_some(if stats.max > stats.min {
+            (stats.min, stats.max)
+        } else {
+            (stats.min - 0.5, stats.max + 0.5)
+        });
+
+        let range = settings
+            .chart_histogram_range
+            .filter(|(min, max)| max > min)
+            .or(data_range);
+        let mut counts = std::mem::take(&mut self.histogram_counts);
+        counts.resize(settings.chart_histogram_bins.max(2), 0);
+        let outside = match (range, self.data.series.get(self.histogram_series)) {
+            (Some(range), Some(series)) => {
+                histogram(series.values.iter().copied(), range, &mut counts)
+            }
+            _ => {
+                counts.fill(0);
+                0
+            }
+        };
 
-                    let y_range = max_y - min_y;
-                    let padding = if y_range > 0.0 { y_range * 0.1 } else { 1.0 };
-                    let padded_min = min_y - padding;
-                    let padded_max = max_y + padding;
+        self.histogram_controls(ui, settings, data_range, outside);
 
-                    let x_max = (self.samples.len() as f32).max(10.0);
+        let (min, max) = range.unwrap_or((0.0, 1.0));
+        let highest = counts.iter().copied().max().unwrap_or(0);
+        let axes = PlotAxes {
+            x_range: (min, max),
+            y_range: (0.0, (highest as f64 * 1.1).max(1.0)),
+            labeled: range.is_some(),
+            y2_range: None,
+            x_labels: true,
+            format_x: |x| format!("{x:.2}"),
+        };
+        let color = self
+            .data
+            .series
+            .get(self.histogram_series)
+            .map_or(Color32::GRAY, |series| series.color);
+        let width = (max - min) / counts.len() as f64;
 
-                    (padded_min, padded_max, 0.0..=x_max)
+        let chart_rect = show_plot(
+            ui,
+            plot_size(available_size),
+            &axes,
+            |_, response, to_screen, shapes| {
+                if range.is_none() {
+                    return;
+                }
+                for (bin, &count) in counts.iter().enumerate() {
+                    if count == 0 {
+                        continue;
+                    }
+                    let left = min + bin as f64 * width;
+                    let bar = Rect::from_two_pos(
+                        to_screen * pos2(left as f32, 0.0),
+                        to_screen * pos2((left + width) as f32, count as f32),
+                    );
+                    shapes.push(epaint::Shape::rect_filled(
+                        bar.shrink2(Vec2::new(0.5, 0.0)),
+                        0.0,
+                        color.gamma_multiply(0.8),
+                    ));
+                }
+
+                // Readout of the bin under the pointer
+                let Some(pointer) = response.hover_pos() else {
+                    return;
                 };
+                let x = (to_screen.inverse() * pointer).x as f64;
+                let bin = (((x - min) / width).max(0.0) as usize).min(counts.len() - 1);
+                let left = min + bin as f64 * width;
+                response.clone().on_hover_ui_at_pointer(|ui| {
+                    ui.label(format!("{left:.3} to {:.3}", left + width));
+                    ui.colored_label(color, format!("{} values", counts[bin]));
+                });
+            },
+        );
+        self.histogram_counts = counts;
+        self.chart_rect = Some(chart_rect);
+    }
 
-                let to_screen = emath::RectTransform::from_to(
-                    Rect::from_x_y_ranges(x_range, max_val..=min_val), // Change this line
-                    rect,
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        available_size: Vec2,
+        _autoscroll: bool,
+        settings: &mut AppSettings,
+    ) {
+        self.x_axis = settings.chart_x_axis;
+        self.layout = settings.chart_layout;
+        self.update_derived(settings);
+        self.save_pending_png(ui.ctx());
+        self.legend(ui, settings);
+
+        if settings.chart_display != ChartDisplay::Time {
+            // Only the time plot can be paused
+            self.pause_requested = false;
+        }
+        match settings.chart_display {
+            ChartDisplay::Time => self.show_time(ui, available_size, settings),
+            ChartDisplay::Spectrum => self.show_spectrum(ui, available_size, settings),
+            ChartDisplay::Triggered => self.show_triggered(ui, available_size, settings),
+            ChartDisplay::Xy => self.show_xy(ui, available_size, settings),
+            ChartDisplay::Histogram => self.show_histogram(ui, available_size, settings),
+        }
+    }
+
+    fn show_time(&mut self, ui: &mut egui::Ui, available_size: Vec2, settings: &mut AppSettings) {
+        let (x_min, x_max) = self
+            .view
+            .x_range
+            .unwrap_or_else(|| self.latest_x_range(settings));
+        let range = self
+            .view
+            .y_range
+            .or_else(|| self.value_range(x_min, x_max, |series| !self.is_on_right_axis(series)));
+        let (min_val, max_val) = range.unwrap_or((-1.0, 1.0));
+        let current = ((x_min, x_max), (min_val, max_val));
+
+        if std::mem::take(&mut self.pause_requested) && !self.view.paused {
+            self.pause(current);
+        }
+
+        self.view_controls(ui, settings, current);
+        self.stats_window(ui.ctx(), self.in_view_indices(x_min, x_max));
+
+        // Limit lines of the visible series: series, level, color and
+        // whether to highlight
+        let limits: Vec<(usize, f64, Color32, bool)> = settings
+            .chart_thresholds
+            .iter()
+            .enumerate()
+            .filter_map(|(index, threshold)| {
+                let series = self
+                    .data
+                    .series
+                    .iter()
+                    .position(|series| series.visible && series.name == threshold.series)?;
+                let alarm = threshold.highlight && self.alarms.is_exceeded(index);
+                Some((
+                    series,
+                    threshold.level,
+                    self.data.series[series].color,
+                    alarm,
+                ))
+            })
+            .collect();
+        let size = plot_size(available_size);
+        let format_x = self.x_formatter();
+
+        if self.layout == ChartLayout::Stacked {
+            let visible: Vec<usize> = (0..self.data.series.len())
+                .filter(|&series| self.data.series[series].visible)
+                .collect();
+            let count = visible.len().max(1);
+            let spacing = ui.spacing().item_spacing.y;
+            let height = ((size.y - spacing * (count - 1) as f32) / count as f32).max(20.0);
+            let indices = self.visible_indices(x_min, x_max);
+
+            let mut chart_rect: Option<Rect> = None;
+            for (position, &series) in visible.iter().enumerate() {
+                let range = self
+                    .view
+                    .stacked_y_ranges
+                    .get(&series)
+                    .copied()
+                    .or_else(|| self.value_range(x_min, x_max, |other| other == series));
+                let axes = PlotAxes {
+                    y_range: range.unwrap_or((-1.0, 1.0)),
+                    labeled: range.is_some(),
+                    x_labels: position + 1 == count,
+                    ..PlotAxes::new(current.0, (-1.0, 1.0), format_x)
+                };
+                let snapshot = [self.series_snapshot(series, indices.clone(), None)];
+                let series_limits: Vec<(f64, Color32, bool)> = limits
+                    .iter()
+                    .filter(|limit| limit.0 == series)
+                    .map(|&(_, level, color, alarm)| (level, color, alarm))
+                    .collect();
+                let rect = self.time_plot(
+                    ui,
+                    Vec2::new(size.x, height),
+                    &axes,
+                    YTarget::Stacked(series),
+                    &snapshot,
+                    &series_limits,
                 );
+                chart_rect = Some(chart_rect.map_or(rect, |united| united.union(rect)));
+            }
+            if visible.is_empty() {
+                let axes = PlotAxes {
+                    labeled: false,
+                    ..PlotAxes::new(current.0, current.1, format_x)
+                };
+                chart_rect = Some(self.time_plot(ui, size, &axes, YTarget::Overlay, &[], &[]));
+            }
+            self.chart_rect = chart_rect;
+            return;
+        }
+
+        let right_range = self.right_range(x_min, x_max);
+        let snapshot = self.snapshot(current);
+        let limits: Vec<(f64, Color32, bool)> = limits
+            .iter()
+            .map(|&(series, level, color, alarm)| {
+                let level = match right_range {
+                    Some(from) if self.is_on_right_axis(series) => rescale(level, from, current.1),
+                    _ => level,
+                };
+                (level, color, alarm)
+            })
+            .collect();
+        let axes = PlotAxes {
+            y2_range: right_range,
+            labeled: range.is_some() || right_range.is_some(),
+            ..PlotAxes::new(current.0, current.1, format_x)
+        };
+        // Room for the labels of the right axis
+        let size = if right_range.is_some() {
+            Vec2::new(size.x - MARGIN_LEFT, size.y)
+        } else {
+            size
+        };
+        let chart_rect =
+            self.time_plot(ui, size, &axes, YTarget::Overlay, &snapshot.series, &limits);
+        self.chart_rect = Some(chart_rect);
+    }
 
-                let mut shapes = vec![];
-
-                // Draw grid lines
-                if !self.samples.is_empty() {
-                    // Horizontal grid lines
-                    for i in 0..=5 {
-                        let y = min_val + (max_val - min_val) * (i as f32 / 5.0);
-                        let start = to_screen * pos2(0.0, y);
-                        let end = to_screen * pos2(self.samples.len() as f32, y);
-                        shapes.push(epaint::Shape::line_segment(
-                            [start, end],
-                            (0.5, Color32::GRAY.gamma_multiply(0.2)),
-                        ));
-                    }
-
-                    // Vertical grid lines
-                    let num_v_lines = 8;
-                    for i in 0..=num_v_lines {
-                        let x = (self.samples.len() as f32) * (i as f32 / num_v_lines as f32);
-                        let start = to_screen * pos2(x, min_val);
-                        let end = to_screen * pos2(x, max_val);
-                        shapes.push(epaint::Shape::line_segment(
-                            [start, end],
-                            (0.5, Color32::GRAY.gamma_multiply(0.2)),
-                        ));
-                    }
-                }
-
-                // Draw data line
-                if !self.samples.is_empty() {
-                    let points: Vec<Pos2> = self
-                        .samples
+    /// Draw series and limit lines (level, color, highlighted) of the time
+    /// plot and handle the pointer on it.
+    fn time_plot(
+        &mut self,
+        ui: &mut egui::Ui,
+        size: Vec2,
+        axes: &PlotAxes,
+        target: YTarget,
+        series: &[SeriesSnapshot],
+        limits: &[(f64, Color32, bool)],
+    ) -> Rect {
+        let (x_min, x_max) = axes.x_range;
+        show_plot(ui, size, axes, |ui, response, to_screen, shapes| {
+            let alarm_color = ui.visuals().error_fg_color;
+            if limits.iter().any(|&(_, _, alarm)| alarm) {
+                shapes.push(epaint::Shape::rect_filled(
+                    response.rect,
+                    0.0,
+                    alarm_color.gamma_multiply(0.12),
+                ));
+            }
+            for &(level, color, alarm) in limits {
+                let line = [
+                    to_screen * pos2(x_min as f32, level as f32),
+                    to_screen * pos2(x_max as f32, level as f32),
+                ];
+                if alarm {
+                    shapes.push(epaint::Shape::line_segment(line, (2.0, alarm_color)));
+                } else {
+                    shapes.extend(epaint::Shape::dashed_line(
+                        &line,
+                        (1.0, color.gamma_multiply(0.8)),
+                        6.0,
+                        4.0,
+                    ));
+                }
+            }
+
+            // Draw one line per series, broken where a record had no value
+            for series in series {
+                for segment in &series.segments {
+                    let points: Vec<Pos2> = segment
                         .iter()
-                        .enumerate()
-                        .map(|(i, &value)| to_screen * pos2(i as f32, value))
+                        .map(|&(x, y)| to_screen * pos2(x as f32, y as f32))
                         .collect();
-
                     shapes.push(epaint::Shape::line(
                         points,
-                        PathStroke::new(2.0, Color32::from_rgb(0, 150, 255)),
+                        PathStroke::new(2.0, series.color),
                     ));
                 }
+            }
 
-                ui.painter().extend(shapes);
-            });
-        });
-
-        // X-axis labels
-        if !self.samples.is_empty() {
-            ui.horizontal(|ui| {
-                ui.add_space(margin_left);
+            self.hover_readout(response, to_screen, shapes);
+            self.handle_interaction(ui, response, to_screen, axes, target);
+        })
+    }
+}
 
-                let num_x_labels = 6;
-                let label_width = plot_size.x / num_x_labels as f32;
+/// Size of the plot area, leaving room for the axis labels and the two rows
+/// of controls above it.
+fn plot_size(available_size: Vec2) -> Vec2 {
+    let margin_bottom = 30.0;
+    Vec2::new(
+        available_size.x - MARGIN_LEFT,
+        (available_size.y * 0.85) - margin_bottom - 48.0,
+    )
+}
 
-                for i in 0..=num_x_labels {
-                    let x_val = (self.samples.len() as f32) * (i as f32 / num_x_labels as f32);
+fn display_combo(ui: &mut egui::Ui, settings: &mut AppSettings) {
+    ui.label("View:");
+    egui::ComboBox::from_id_salt("chart_display_combo")
+        .selected_text(settings.chart_display.to_string())
+        .show_ui(ui, |ui| {
+            for display in ChartDisplay::ALL {
+                ui.selectable_value(&mut settings.chart_display, display, display.to_string());
+            }
+        });
+}
 
-                    ui.allocate_ui_with_layout(
-                        Vec2::new(label_width, 20.0),
-                        egui::Layout::centered_and_justified(egui::Direction::TopDown),
-                        |ui| {
-                            ui.label(format!("{x_val:.0}"));
-                        },
-                    );
-                }
-            });
-        }
+/// Append `extension` unless `path` already ends with it.
+fn with_extension(path: &str, extension: &str) -> String {
+    if path.to_lowercase().ends_with(&format!(".{extension}")) {
+        path.to_string()
+    } else {
+        format!("{path}.{extension}")
     }
 }
 
@@ -228,9 +1928,7 @@ impl<'de> serde::Deserialize<'de> for ChartPanel {
         let content = VecDeque::deserialize(deserializer)?;
         Ok(Self {
             content,
-            should_scroll_to_bottom: false,
-            samples: VecDeque::new(),
-            stream_buffer: String::new(),
+            ..Self::default()
         })
     }
 }
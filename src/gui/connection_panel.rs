@@ -18,12 +18,15 @@ impl ConnectionPanel {
         }
     }
 
+    /// Show the port settings and the open/close button. Returns true when
+    /// the port was opened.
     pub fn show(
         &mut self,
         ui: &mut egui::Ui,
         manager: &mut Option<Box<dyn CommunicationManager>>,
         events_rx: &mut Option<mpsc::Receiver<CommunicationEvent>>,
-    ) {
+    ) -> bool {
+        let mut opened = false;
         ui.horizontal_wrapped(|ui| {
             // Update ports button
             if ui.button("Update ports").clicked() {
@@ -100,11 +103,30 @@ impl ConnectionPanel {
                     }
                 });
 
+            // Transmit pacing for devices with small receive buffers
+            ui.label("Char delay");
+            ui.add(
+                egui::DragValue::new(&mut self.port_settings.char_delay_ms)
+                    .range(0..=1000)
+                    .suffix(" ms"),
+            )
+            .on_hover_text("Pause after each transmitted byte. Applied when the port is opened.");
+            ui.label("Line delay");
+            ui.add(
+                egui::DragValue::new(&mut self.port_settings.line_delay_ms)
+                    .range(0..=10_000)
+                    .suffix(" ms"),
+            )
+            .on_hover_text(
+                "Extra pause after each transmitted newline. Applied when the port is opened.",
+            );
+
             // Connect/Disconnect button
             if ui.button(self.button_text.clone()).clicked() {
-                self.handle_connection_button(manager, events_rx);
+                opened = self.handle_connection_button(manager, events_rx);
             }
         });
+        opened
     }
 
     pub fn update_ports(&mut self, manager: &mut Option<Box<dyn CommunicationManager>>) {
@@ -120,11 +142,12 @@ impl ConnectionPanel {
         }
     }
 
+    /// Open or close the port. Returns true when the port was opened.
     fn handle_connection_button(
         &mut self,
         manager: &mut Option<Box<dyn CommunicationManager>>,
         events_rx: &mut Option<mpsc::Receiver<CommunicationEvent>>,
-    ) {
+    ) -> bool {
         if let Some(ref mut manager) = manager {
             if manager.is_running() {
                 if let Err(e) = manager.stop() {
@@ -134,7 +157,7 @@ impl ConnectionPanel {
             } else {
                 if let Err(e) = manager.update_settings(&self.port_settings) {
                     eprintln!("Error updating port settings: {e}");
-                    return;
+                    return false;
                 }
                 let (tx, rx) = mpsc::channel();
                 if let Err(e) = manager.start(tx) {
@@ -142,11 +165,13 @@ impl ConnectionPanel {
                 } else {
                     self.button_text = "Close port".to_string();
                     *events_rx = Some(rx);
+                    return true;
                 }
             }
         } else {
             eprintln!("Serial manager is not initialized.");
         }
+        false
     }
 
     pub fn update_button_text(&mut self, manager: &Option<Box<dyn CommunicationManager>>) {
@@ -0,0 +1,99 @@
+use crate::chart::expression::{DerivedSeries, Expression};
+
+const HELP: &str = "Operators: + - * / % ^ and parentheses\n\
+Series: by name, e.g. ax, or in brackets, e.g. [Series 1]\n\
+Functions: sqrt abs sin cos tan asin acos atan atan2 exp ln log10 floor ceil round min max\n\
+avg(x, n): moving average over n samples\n\
+diff(x): change since the previous sample\n\
+deriv(x): change per second\n\
+Constant: pi";
+
+/// Editor for the series computed from other series.
+pub struct DerivedPanel {
+    pub open: bool,
+}
+
+impl DerivedPanel {
+    pub fn new() -> Self {
+        Self { open: false }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, derived: &mut Vec<DerivedSeries>) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Derived series")
+            .open(&mut open)
+            .default_size([600.0, 250.0])
+            .show(ctx, |ui| {
+                let mut remove = None;
+                egui::ScrollArea::vertical()
+                    .max_height(250.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("derived_series_grid")
+                            .striped(true)
+                            .num_columns(4)
+                            .show(ui, |ui| {
+                                ui.strong("Name");
+                                ui.strong("Expression");
+                                ui.label("");
+                                ui.label("");
+                                ui.end_row();
+
+                                for (index, series) in derived.iter_mut().enumerate() {
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut series.name)
+                                            .desired_width(100.0),
+                                    );
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut series.expression)
+                                            .hint_text("e.g. (raw-512)*0.0048")
+                                            .desired_width(300.0),
+                                    );
+                                    match Expression::parse(&series.expression) {
+                                        Ok(_) if series.name.trim().is_empty() => {
+                                            ui.colored_label(
+                                                ui.visuals().error_fg_color,
+                                                "Name missing",
+                                            );
+                                        }
+                                        Ok(_) => {
+                                            ui.label("✔");
+                                        }
+                                        Err(err) => {
+                                            ui.colored_label(ui.visuals().error_fg_color, err);
+                                        }
+                                    }
+                                    if ui.button("🗑").clicked() {
+                                        remove = Some(index);
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                if let Some(index) = remove {
+                    derived.remove(index);
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Add series").clicked() {
+                        derived.push(DerivedSeries {
+                            name: format!("f{}", derived.len() + 1),
+                            expression: String::new(),
+                        });
+                    }
+                    ui.label("ℹ").on_hover_text(HELP);
+                });
+            });
+        self.open = open;
+    }
+}
+
+impl Default for DerivedPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,167 @@
+use crate::communicationtrait::CommunicationManager;
+use crate::file_sender::{FileSendOptions, FileSendState, FileSender};
+use std::time::Duration;
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct FileSendPanel {
+    pub file_path: String,
+    pub chunk_size: usize,
+    pub chunk_delay_ms: u64,
+    pub line_delay_ms: u64,
+
+    #[serde(skip)]
+    pub open: bool,
+    #[serde(skip)]
+    transfer: Option<FileSender>,
+    #[serde(skip)]
+    error: Option<String>,
+}
+
+impl FileSendPanel {
+    pub fn new() -> Self {
+        Self {
+            file_path: String::new(),
+            chunk_size: 64,
+            chunk_delay_ms: 0,
+            line_delay_ms: 0,
+            open: false,
+            transfer: None,
+            error: None,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        manager: &mut Option<Box<dyn CommunicationManager>>,
+    ) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Send file")
+            .open(&mut open)
+            .resizable(false)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                let sending = self
+                    .transfer
+                    .as_ref()
+                    .is_some_and(|transfer| transfer.state() == FileSendState::Sending);
+
+                ui.add_enabled_ui(!sending, |ui| {
+                    egui::Grid::new("file_send_grid")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("File:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.file_path)
+                                    .hint_text("Path of the file to send")
+                                    .desired_width(320.0),
+                            );
+                            ui.end_row();
+
+                            ui.label("Chunk size:");
+                            ui.add(
+                                egui::DragValue::new(&mut self.chunk_size)
+                                    .range(1..=65536)
+                                    .suffix(" bytes"),
+                            );
+                            ui.end_row();
+
+                            ui.label("Delay between chunks:");
+                            ui.add(
+                                egui::DragValue::new(&mut self.chunk_delay_ms)
+                                    .range(0..=10_000)
+                                    .suffix(" ms"),
+                            );
+                            ui.end_row();
+
+                            ui.label("Delay after each line:");
+                            ui.add(
+                                egui::DragValue::new(&mut self.line_delay_ms)
+                                    .range(0..=10_000)
+                                    .suffix(" ms"),
+                            );
+                            ui.end_row();
+                        });
+                });
+
+                ui.horizontal(|ui| {
+                    if sending {
+                        if ui.button("Cancel").clicked() {
+                            if let Some(transfer) = &mut self.transfer {
+                                transfer.cancel();
+                            }
+                        }
+                    } else if ui.button("Send").clicked() {
+                        self.start(ctx, manager);
+                    }
+                });
+
+                if let Some(transfer) = &self.transfer {
+                    Self::progress(ui, transfer);
+                }
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+            });
+        self.open = open;
+    }
+
+    fn start(&mut self, ctx: &egui::Context, manager: &mut Option<Box<dyn CommunicationManager>>) {
+        self.error = None;
+        let Some(sender) = manager.as_ref().and_then(|manager| manager.data_sender()) else {
+            self.error = Some("Port is not open".to_string());
+            return;
+        };
+
+        let options = FileSendOptions {
+            chunk_size: self.chunk_size,
+            chunk_delay: Duration::from_millis(self.chunk_delay_ms),
+            line_delay: Duration::from_millis(self.line_delay_ms),
+        };
+        match FileSender::start(&self.file_path, sender, options, ctx.clone()) {
+            Ok(transfer) => self.transfer = Some(transfer),
+            Err(e) => self.error = Some(format!("Cannot read \"{}\": {e}", self.file_path)),
+        }
+    }
+
+    fn progress(ui: &mut egui::Ui, transfer: &FileSender) {
+        let sent = transfer.sent_bytes();
+        let total = transfer.total_bytes();
+        let fraction = if total > 0 {
+            sent as f32 / total as f32
+        } else {
+            1.0
+        };
+
+        ui.add(egui::ProgressBar::new(fraction).show_percentage());
+        ui.label(format!(
+            "{sent} / {total} bytes, {:.0} B/s, {:.1} s",
+            transfer.throughput(),
+            transfer.elapsed().as_secs_f32()
+        ));
+
+        match transfer.state() {
+            FileSendState::Sending => {}
+            FileSendState::Done => {
+                ui.label("Done");
+            }
+            FileSendState::Cancelled => {
+                ui.label("Cancelled");
+            }
+            FileSendState::Failed(err) => {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+            }
+        }
+    }
+}
+
+impl Default for FileSendPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
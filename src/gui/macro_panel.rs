@@ -0,0 +1,223 @@
+use super::send_panel::{send_to_manager, trailer_menu};
+use crate::communicationtrait::CommunicationManager;
+use crate::generalsettings::AppSettings;
+use crate::macros::{export_macros, import_macros, Macro};
+use crate::payload::{LineEnding, PayloadMode};
+
+pub struct MacroPanel {
+    show_manager: bool,
+    file_path: String,
+    status: Option<String>,
+}
+
+impl MacroPanel {
+    pub fn new() -> Self {
+        Self {
+            show_manager: false,
+            file_path: "macros.json".to_string(),
+            status: None,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        manager: &mut Option<Box<dyn CommunicationManager>>,
+        settings: &mut AppSettings,
+    ) {
+        self.handle_shortcuts(ui.ctx(), manager, &settings.macros);
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Macros:");
+            for macro_def in &settings.macros {
+                let encoded = macro_def.to_bytes();
+                let mut hover = macro_def.payload.clone();
+                if let Some(shortcut) = macro_def.keyboard_shortcut() {
+                    hover = format!("{hover}\n({})", ui.ctx().format_shortcut(&shortcut));
+                }
+                if let Err(err) = &encoded {
+                    hover = format!("{hover}\n{err}");
+                }
+
+                let response = ui
+                    .add_enabled(encoded.is_ok(), egui::Button::new(&macro_def.name))
+                    .on_hover_text(&hover)
+                    .on_disabled_hover_text(&hover);
+                if response.clicked() {
+                    if let Ok(data) = encoded {
+                        send_to_manager(manager, data);
+                    }
+                }
+            }
+
+            if ui.button("Edit macros...").clicked() {
+                self.show_manager = !self.show_manager;
+            }
+        });
+
+        self.manager_window(ui.ctx(), &mut settings.macros);
+    }
+
+    fn handle_shortcuts(
+        &self,
+        ctx: &egui::Context,
+        manager: &mut Option<Box<dyn CommunicationManager>>,
+        macros: &[Macro],
+    ) {
+        let typing = ctx.wants_keyboard_input();
+        for macro_def in macros {
+            let Some(shortcut) = macro_def.keyboard_shortcut() else {
+                continue;
+            };
+            // Plain keys belong to the focused text field
+            if typing && shortcut.modifiers.is_none() {
+                continue;
+            }
+            if ctx.input_mut(|i| i.consume_shortcut(&shortcut)) {
+                match macro_def.to_bytes() {
+                    Ok(data) => {
+                        send_to_manager(manager, data);
+                    }
+                    Err(err) => eprintln!("Macro \"{}\" is invalid: {err}", macro_def.name),
+                }
+            }
+        }
+    }
+
+    fn manager_window(&mut self, ctx: &egui::Context, macros: &mut Vec<Macro>) {
+        if !self.show_manager {
+            return;
+        }
+
+        let mut open = true;
+        egui::Window::new("Macro manager")
+            .open(&mut open)
+            .default_size([700.0, 300.0])
+            .show(ctx, |ui| {
+                let mut remove = None;
+                let mut swap = None;
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("macro_grid")
+                            .striped(true)
+                            .num_columns(7)
+                            .show(ui, |ui| {
+                                ui.strong("Name");
+                                ui.strong("Payload");
+                                ui.strong("Mode");
+                                ui.strong("Checksum");
+                                ui.strong("Line ending");
+                                ui.strong("Shortcut");
+                                ui.label("");
+                                ui.end_row();
+
+                                let count = macros.len();
+                                for (index, macro_def) in macros.iter_mut().enumerate() {
+                                    Self::macro_row(ui, index, macro_def);
+
+                                    ui.horizontal(|ui| {
+                                        if ui
+                                            .add_enabled(index > 0, egui::Button::new("⏶"))
+                                            .clicked()
+                                        {
+                                            swap = Some((index - 1, index));
+                                        }
+                                        if ui
+                                            .add_enabled(index + 1 < count, egui::Button::new("⏷"))
+                                            .clicked()
+                                        {
+                                            swap = Some((index, index + 1));
+                                        }
+                                        if ui.button("🗑").clicked() {
+                                            remove = Some(index);
+                                        }
+                                    });
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                if let Some((a, b)) = swap {
+                    macros.swap(a, b);
+                }
+                if let Some(index) = remove {
+                    macros.remove(index);
+                }
+
+                if ui.button("Add macro").clicked() {
+                    macros.push(Macro::default());
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("File:");
+                    ui.text_edit_singleline(&mut self.file_path);
+                    if ui.button("Import").clicked() {
+                        self.status = Some(match import_macros(&self.file_path) {
+                            Ok(imported) => {
+                                let message = format!("Imported {} macros", imported.len());
+                                macros.extend(imported);
+                                message
+                            }
+                            Err(e) => format!("Import failed: {e}"),
+                        });
+                    }
+                    if ui.button("Export").clicked() {
+                        self.status = Some(match export_macros(&self.file_path, macros) {
+                            Ok(()) => format!("Exported {} macros", macros.len()),
+                            Err(e) => format!("Export failed: {e}"),
+                        });
+                    }
+                });
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+            });
+        self.show_manager = open;
+    }
+
+    fn macro_row(ui: &mut egui::Ui, index: usize, macro_def: &mut Macro) {
+        ui.add(egui::TextEdit::singleline(&mut macro_def.name).desired_width(100.0));
+
+        let payload_edit =
+            ui.add(egui::TextEdit::singleline(&mut macro_def.payload).desired_width(200.0));
+        if let Err(err) = macro_def.to_bytes() {
+            payload_edit.on_hover_text(err);
+        }
+
+        egui::ComboBox::from_id_salt(("macro_mode", index))
+            .selected_text(macro_def.mode.to_string())
+            .show_ui(ui, |ui| {
+                for mode in PayloadMode::ALL {
+                    ui.selectable_value(&mut macro_def.mode, mode, mode.to_string());
+                }
+            });
+
+        trailer_menu(ui, &mut macro_def.trailer, ("macro_trailer", index));
+
+        egui::ComboBox::from_id_salt(("macro_line_ending", index))
+            .selected_text(macro_def.line_ending.to_string())
+            .show_ui(ui, |ui| {
+                for ending in LineEnding::ALL {
+                    ui.selectable_value(&mut macro_def.line_ending, ending, ending.to_string());
+                }
+            });
+
+        let shortcut_edit = ui.add(
+            egui::TextEdit::singleline(&mut macro_def.shortcut)
+                .hint_text("e.g. F5, Ctrl+1")
+                .desired_width(90.0),
+        );
+        if !macro_def.shortcut.trim().is_empty() && macro_def.keyboard_shortcut().is_none() {
+            shortcut_edit.on_hover_text("Invalid shortcut");
+        }
+    }
+}
+
+impl Default for MacroPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -17,11 +17,24 @@ impl MenuBar {
         ctx: &egui::Context,
         clear_callback: impl FnOnce(),
         show_info_callback: impl FnOnce(),
+        send_file_callback: impl FnOnce(),
+        file_transfer_callback: impl FnOnce(),
         settings: &mut AppSettings,
     ) {
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
+                    if ui.button("Send file...").clicked() {
+                        send_file_callback();
+                        ui.close_menu();
+                    }
+                    if ui
+                        .button("File transfer (XMODEM/YMODEM/ZMODEM)...")
+                        .clicked()
+                    {
+                        file_transfer_callback();
+                        ui.close_menu();
+                    }
                     if ui.button("Info").clicked() {
                         show_info_callback();
                         ui.close_menu();
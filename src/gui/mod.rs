@@ -1,15 +1,27 @@
+pub mod binary_format_panel;
 pub mod chart_panel;
 pub mod connection_panel;
+pub mod derived_panel;
 pub mod file_log_panel;
+pub mod file_send_panel;
+pub mod macro_panel;
 pub mod menu_bar;
+pub mod plot;
+pub mod repeat_panel;
 pub mod rx_panel;
 pub mod send_panel;
 pub mod settings_panel;
+pub mod threshold_panel;
+pub mod transfer_panel;
 
 // Re-export para facilitar el uso
 pub use chart_panel::ChartPanel;
 pub use connection_panel::ConnectionPanel;
 pub use file_log_panel::FileLogPanel;
+pub use file_send_panel::FileSendPanel;
+pub use macro_panel::MacroPanel;
 pub use menu_bar::MenuBar;
+pub use repeat_panel::RepeatPanel;
 pub use rx_panel::RxPanel;
 pub use send_panel::SendPanel;
+pub use transfer_panel::TransferPanel;
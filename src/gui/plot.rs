@@ -0,0 +1,171 @@
+use egui::{containers::Frame, emath, epaint, pos2, Color32, Rect, Vec2};
+
+/// Width reserved left of the plot for the Y axis labels.
+pub const MARGIN_LEFT: f32 = 60.0;
+
+/// Axis ranges and labels of a plot drawn by [`show_plot`].
+pub struct PlotAxes {
+    pub x_range: (f64, f64),
+    pub y_range: (f64, f64),
+    /// Range of a second Y axis labeled on the right. Its values must be
+    /// drawn rescaled to `y_range`.
+    pub y2_range: Option<(f64, f64)>,
+    /// Draw the grid and axis labels; off while there is nothing to show.
+    pub labeled: bool,
+    /// Draw the X labels below the plot; off for all but the lowest of
+    /// stacked plots.
+    pub x_labels: bool,
+    pub format_x: fn(f64) -> String,
+}
+
+impl PlotAxes {
+    pub fn new(x_range: (f64, f64), y_range: (f64, f64), format_x: fn(f64) -> String) -> Self {
+        Self {
+            x_range,
+            y_range,
+            y2_range: None,
+            labeled: true,
+            x_labels: true,
+            format_x,
+        }
+    }
+}
+
+/// Map `value` from the range `from` onto the range `to`.
+pub fn rescale(value: f64, from: (f64, f64), to: (f64, f64)) -> f64 {
+    to.0 + (value - from.0) * (to.1 - to.0) / (from.1 - from.0)
+}
+
+/// Draw a plot area of `plot_size` with Y labels on its left, a grid and X
+/// labels below. `add_contents` adds the shapes of the data and handles the
+/// pointer. Returns the screen area of the plot and its labels.
+pub fn show_plot(
+    ui: &mut egui::Ui,
+    plot_size: Vec2,
+    axes: &PlotAxes,
+    add_contents: impl FnOnce(
+        &mut egui::Ui,
+        &egui::Response,
+        &emath::RectTransform,
+        &mut Vec<epaint::Shape>,
+    ),
+) -> Rect {
+    let (x_min, x_max) = axes.x_range;
+    let (min_val, max_val) = axes.y_range;
+
+    ui.scope(|ui| {
+        ui.horizontal(|ui| {
+            if axes.labeled {
+                y_labels(ui, plot_size.y, axes.y_range, egui::Align::RIGHT);
+            } else {
+                ui.add_space(MARGIN_LEFT - 10.0);
+            }
+
+            // Plot area
+            Frame::canvas(ui.style()).show(ui, |ui| {
+                ui.ctx().request_repaint();
+
+                let (rect, response) =
+                    ui.allocate_exact_size(plot_size, egui::Sense::click_and_drag());
+
+                let to_screen = emath::RectTransform::from_to(
+                    Rect::from_x_y_ranges(
+                        x_min as f32..=x_max as f32,
+                        max_val as f32..=min_val as f32,
+                    ),
+                    rect,
+                );
+
+                let mut shapes = vec![];
+
+                if axes.labeled {
+                    // Horizontal grid lines
+                    for i in 0..=5 {
+                        let y = (min_val + (max_val - min_val) * (i as f64 / 5.0)) as f32;
+                        let start = to_screen * pos2(x_min as f32, y);
+                        let end = to_screen * pos2(x_max as f32, y);
+                        shapes.push(epaint::Shape::line_segment(
+                            [start, end],
+                            (0.5, Color32::GRAY.gamma_multiply(0.2)),
+                        ));
+                    }
+
+                    // Vertical grid lines
+                    let num_v_lines = 8;
+                    for i in 0..=num_v_lines {
+                        let x = (x_min + (x_max - x_min) * (i as f64 / num_v_lines as f64)) as f32;
+                        let start = to_screen * pos2(x, min_val as f32);
+                        let end = to_screen * pos2(x, max_val as f32);
+                        shapes.push(epaint::Shape::line_segment(
+                            [start, end],
+                            (0.5, Color32::GRAY.gamma_multiply(0.2)),
+                        ));
+                    }
+                }
+
+                add_contents(ui, &response, &to_screen, &mut shapes);
+                ui.painter_at(rect).extend(shapes);
+            });
+
+            if let (true, Some(y2_range)) = (axes.labeled, axes.y2_range) {
+                y_labels(ui, plot_size.y, y2_range, egui::Align::LEFT);
+            }
+        });
+
+        // X-axis labels
+        if axes.labeled && axes.x_labels {
+            ui.horizontal(|ui| {
+                ui.add_space(MARGIN_LEFT);
+
+                let num_x_labels = 6;
+                let label_width = plot_size.x / num_x_labels as f32;
+
+                for i in 0..=num_x_labels {
+                    let x_val = x_min + (x_max - x_min) * (i as f64 / num_x_labels as f64);
+
+                    ui.allocate_ui_with_layout(
+                        Vec2::new(label_width, 20.0),
+                        egui::Layout::centered_and_justified(egui::Direction::TopDown),
+                        |ui| {
+                            ui.label((axes.format_x)(x_val));
+                        },
+                    );
+                }
+            });
+        }
+    })
+    .response
+    .rect
+}
+
+/// Six labels from the top to the bottom of `range`, beside a plot of
+/// `height`.
+fn y_labels(ui: &mut egui::Ui, height: f32, (min_val, max_val): (f64, f64), align: egui::Align) {
+    let layout = if align == egui::Align::RIGHT {
+        egui::Layout::right_to_left(egui::Align::Center)
+    } else {
+        egui::Layout::left_to_right(egui::Align::Center)
+    };
+    ui.allocate_ui_with_layout(
+        Vec2::new(MARGIN_LEFT - 10.0, height),
+        egui::Layout::top_down(align),
+        |ui| {
+            for i in 0..=5 {
+                let y_val = min_val + (max_val - min_val) * (1.0 - i as f64 / 5.0);
+                let y_pos = (height / 5.0) * i as f32;
+
+                ui.allocate_new_ui(
+                    egui::UiBuilder::new().max_rect(Rect::from_min_size(
+                        ui.min_rect().min + egui::vec2(0.0, y_pos - 8.0),
+                        Vec2::new(MARGIN_LEFT - 15.0, 16.0),
+                    )),
+                    |ui| {
+                        ui.with_layout(layout, |ui| {
+                            ui.label(format!("{y_val:.1}"));
+                        });
+                    },
+                );
+            }
+        },
+    );
+}
@@ -0,0 +1,161 @@
+use crate::communicationtrait::CommunicationManager;
+use crate::macros::Macro;
+use crate::repeat_sender::RepeatSender;
+use std::time::Duration;
+
+/// What the periodic sender transmits.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum RepeatSource {
+    /// The current contents of the send box.
+    SendBox,
+    /// The macro with this name.
+    Macro(String),
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct RepeatPanel {
+    pub source: RepeatSource,
+    pub interval_ms: u64,
+    /// Number of messages to send, 0 repeats until stopped.
+    pub repeat_count: u64,
+
+    #[serde(skip)]
+    sender: Option<RepeatSender>,
+    #[serde(skip)]
+    error: Option<String>,
+}
+
+impl RepeatPanel {
+    pub fn new() -> Self {
+        Self {
+            source: RepeatSource::SendBox,
+            interval_ms: 1000,
+            repeat_count: 0,
+            sender: None,
+            error: None,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        manager: &mut Option<Box<dyn CommunicationManager>>,
+        send_box: Result<Vec<u8>, String>,
+        macros: &[Macro],
+    ) {
+        let running = self.is_running();
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Repeat");
+            ui.add_enabled_ui(!running, |ui| {
+                egui::ComboBox::from_id_salt("repeat_source_combo")
+                    .selected_text(self.source_label())
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut self.source,
+                            RepeatSource::SendBox,
+                            "Send box message",
+                        );
+                        for macro_def in macros {
+                            ui.selectable_value(
+                                &mut self.source,
+                                RepeatSource::Macro(macro_def.name.clone()),
+                                format!("Macro: {}", macro_def.name),
+                            );
+                        }
+                    });
+                ui.label("every");
+                ui.add(
+                    egui::DragValue::new(&mut self.interval_ms)
+                        .range(1..=3_600_000)
+                        .suffix(" ms"),
+                );
+                ui.add(egui::DragValue::new(&mut self.repeat_count).range(0..=1_000_000));
+                ui.label("times (0 = until stopped)");
+            });
+
+            if running {
+                if ui.button("Stop").clicked() {
+                    self.stop();
+                }
+            } else if ui.button("Start").clicked() {
+                self.start(ui.ctx(), manager, send_box, macros);
+            }
+
+            if let Some(sender) = &self.sender {
+                let sent = sender.sent_count();
+                if self.repeat_count > 0 {
+                    ui.label(format!("Sent: {sent}/{}", self.repeat_count));
+                } else {
+                    ui.label(format!("Sent: {sent}"));
+                }
+            }
+            if let Some(error) = &self.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        });
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.sender.as_ref().is_some_and(RepeatSender::is_running)
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(sender) = &mut self.sender {
+            sender.stop();
+        }
+    }
+
+    fn start(
+        &mut self,
+        ctx: &egui::Context,
+        manager: &mut Option<Box<dyn CommunicationManager>>,
+        send_box: Result<Vec<u8>, String>,
+        macros: &[Macro],
+    ) {
+        self.error = None;
+
+        let data = match &self.source {
+            RepeatSource::SendBox => send_box,
+            RepeatSource::Macro(name) => macros
+                .iter()
+                .find(|macro_def| &macro_def.name == name)
+                .ok_or_else(|| format!("Macro \"{name}\" not found"))
+                .and_then(Macro::to_bytes),
+        };
+        let data = match data {
+            Ok(data) if data.is_empty() => Err("Nothing to send".to_string()),
+            other => other,
+        };
+
+        let sender = manager.as_ref().and_then(|manager| manager.data_sender());
+        match (data, sender) {
+            (Ok(data), Some(sender)) => {
+                let limit = (self.repeat_count > 0).then_some(self.repeat_count);
+                self.sender = Some(RepeatSender::start(
+                    sender,
+                    data,
+                    Duration::from_millis(self.interval_ms),
+                    limit,
+                    ctx.clone(),
+                ));
+            }
+            (Err(err), _) => self.error = Some(err),
+            (_, None) => self.error = Some("Port is not open".to_string()),
+        }
+    }
+
+    fn source_label(&self) -> String {
+        match &self.source {
+            RepeatSource::SendBox => "Send box message".to_string(),
+            RepeatSource::Macro(name) => format!("Macro: {name}"),
+        }
+    }
+}
+
+impl Default for RepeatPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,16 +1,46 @@
+use crate::checksum::{ByteOrder, ChecksumKind, Trailer};
 use crate::communicationtrait::CommunicationManager;
 use crate::generalsettings::AppSettings;
+use crate::payload::{self, PayloadMode};
 use egui::Vec2;
+use std::collections::VecDeque;
+
+/// A message as it was typed, so recalling it also restores its input mode.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct SentMessage {
+    pub text: String,
+    pub mode: PayloadMode,
+}
 
 #[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct SendPanel {
     pub message: String,
+    pub mode: PayloadMode,
+    pub history: VecDeque<SentMessage>,
+    pub trailer: Trailer,
+
+    #[serde(skip)]
+    history_index: Option<usize>,
+    #[serde(skip)]
+    draft: Option<SentMessage>,
+    #[serde(skip)]
+    show_history_search: bool,
+    #[serde(skip)]
+    history_filter: String,
 }
 
 impl SendPanel {
     pub fn new() -> Self {
         Self {
             message: String::new(),
+            mode: PayloadMode::Text,
+            history: VecDeque::new(),
+            trailer: Trailer::default(),
+            history_index: None,
+            draft: None,
+            show_history_search: false,
+            history_filter: String::new(),
         }
     }
 
@@ -21,35 +51,277 @@ impl SendPanel {
         available_size: Vec2,
         settings: &AppSettings,
     ) {
+        let input_id = egui::Id::new("send_message_input");
+        // Apply a lowered history size right away, not on the next send
+        self.trim_history(settings.send_history_size);
+        if ui.memory(|mem| mem.has_focus(input_id)) {
+            self.handle_history_keys(ui, input_id);
+        }
+
+        let encoded = self.encoded();
+
         ui.horizontal(|ui| {
-            let text_size = Vec2::new(available_size.x * 0.9, 20.0);
-            ui.add_sized(text_size, egui::TextEdit::singleline(&mut self.message));
+            egui::ComboBox::from_id_salt("send_mode_combo")
+                .width(70.0)
+                .selected_text(self.mode.to_string())
+                .show_ui(ui, |ui| {
+                    for mode in PayloadMode::ALL {
+                        ui.selectable_value(&mut self.mode, mode, mode.to_string());
+                    }
+                });
+
+            let text_size = Vec2::new(available_size.x * 0.75, 20.0);
+            ui.add_sized(
+                text_size,
+                egui::TextEdit::singleline(&mut self.message).id(input_id),
+            );
+
+            trailer_menu(ui, &mut self.trailer, "send_trailer");
 
-            if ui.button("Send").clicked() {
-                self.send_message(manager, settings.clear_message_after_send);
+            if ui
+                .add_enabled(encoded.is_ok(), egui::Button::new("Send"))
+                .clicked()
+            {
+                if let Ok(data) = &encoded {
+                    self.send_message(manager, data.clone(), settings);
+                }
+            }
+
+            if ui
+                .button("History")
+                .on_hover_text("Search previously sent messages")
+                .clicked()
+            {
+                self.show_history_search = !self.show_history_search;
             }
         });
+
+        if let Err(err) = &encoded {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+
+        self.history_search_popup(ui.ctx(), input_id);
+    }
+
+    /// The current message converted to bytes according to the selected mode,
+    /// with the checksum trailer appended.
+    pub fn encoded(&self) -> Result<Vec<u8>, String> {
+        let mut data = payload::encode(&self.message, self.mode)?;
+        self.trailer.append(&mut data)?;
+        Ok(data)
     }
 
     fn send_message(
         &mut self,
         manager: &mut Option<Box<dyn CommunicationManager>>,
-        clear_after_send: bool,
+        data: Vec<u8>,
+        settings: &AppSettings,
     ) {
-        if let Some(ref mut manager) = manager {
-            if manager.is_running() {
-                if let Err(e) = manager.send_data(self.message.as_bytes().to_vec()) {
-                    eprintln!("Error sending data: {e}");
-                } else if clear_after_send {
-                    self.message.clear();
-                }
+        if send_to_manager(manager, data) {
+            self.push_history(settings.send_history_size);
+            if settings.clear_message_after_send {
+                self.message.clear();
+            }
+        }
+    }
+
+    /// Store the current message as the newest history entry, dropping any
+    /// older duplicate and the oldest entries beyond `max_size`.
+    fn push_history(&mut self, max_size: usize) {
+        self.reset_recall();
+        if self.message.is_empty() {
+            return;
+        }
+
+        let entry = SentMessage {
+            text: self.message.clone(),
+            mode: self.mode,
+        };
+        self.history.retain(|old| *old != entry);
+        self.history.push_back(entry);
+        self.trim_history(max_size);
+    }
+
+    /// Drop the oldest history entries beyond `max_size`.
+    fn trim_history(&mut self, max_size: usize) {
+        if self.history.len() <= max_size {
+            return;
+        }
+        while self.history.len() > max_size {
+            self.history.pop_front();
+        }
+        // Indices shifted; restart recall from the newest entry
+        self.reset_recall();
+    }
+
+    /// Forget the entry being recalled and the message typed before it.
+    fn reset_recall(&mut self) {
+        self.history_index = None;
+        self.draft = None;
+    }
+
+    fn handle_history_keys(&mut self, ui: &egui::Ui, input_id: egui::Id) {
+        let (up, down) = ui.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+            )
+        });
+        if self.history.is_empty() || (!up && !down) {
+            return;
+        }
+
+        let next_index = match (self.history_index, up) {
+            (None, true) => Some(self.history.len() - 1),
+            (None, false) => return,
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) if index + 1 < self.history.len() => Some(index + 1),
+            (Some(_), false) => None,
+        };
+
+        if self.history_index.is_none() {
+            self.draft = Some(SentMessage {
+                text: self.message.clone(),
+                mode: self.mode,
+            });
+        }
+        self.history_index = next_index;
+
+        let recalled = match next_index {
+            Some(index) => self.history.get(index).cloned(),
+            None => self.draft.take(),
+        };
+        if let Some(entry) = recalled {
+            self.set_message(ui.ctx(), input_id, entry);
+        }
+    }
+
+    fn set_message(&mut self, ctx: &egui::Context, input_id: egui::Id, entry: SentMessage) {
+        self.message = entry.text;
+        self.mode = entry.mode;
+
+        // Move the cursor to the end of the recalled text
+        if let Some(mut state) = egui::TextEdit::load_state(ctx, input_id) {
+            let end = egui::text::CCursor::new(self.message.chars().count());
+            state
+                .cursor
+                .set_char_range(Some(egui::text::CCursorRange::one(end)));
+            state.store(ctx, input_id);
+        }
+    }
+
+    fn history_search_popup(&mut self, ctx: &egui::Context, input_id: egui::Id) {
+        if !self.show_history_search {
+            return;
+        }
+
+        let mut open = true;
+        let mut selected = None;
+        egui::Window::new("Send history")
+            .open(&mut open)
+            .collapsible(false)
+            .default_size([400.0, 300.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Search:");
+                    ui.text_edit_singleline(&mut self.history_filter);
+                    if ui.button("Clear history").clicked() {
+                        self.history.clear();
+                        self.reset_recall();
+                    }
+                });
+                ui.separator();
+
+                let filter = self.history_filter.to_lowercase();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for entry in self
+                        .history
+                        .iter()
+                        .rev()
+                        .filter(|entry| entry.text.to_lowercase().contains(&filter))
+                    {
+                        let label = format!("[{}] {}", entry.mode, entry.text);
+                        if ui.selectable_label(false, label).clicked() {
+                            selected = Some(entry.clone());
+                        }
+                    }
+                });
+            });
+
+        if let Some(entry) = selected {
+            self.reset_recall();
+            self.set_message(ctx, input_id, entry);
+            ctx.memory_mut(|mem| mem.request_focus(input_id));
+            open = false;
+        }
+        self.show_history_search = open;
+    }
+}
+
+/// Send `data` through the manager if the port is open. Returns true on success.
+pub fn send_to_manager(manager: &mut Option<Box<dyn CommunicationManager>>, data: Vec<u8>) -> bool {
+    if let Some(ref mut manager) = manager {
+        if manager.is_running() {
+            if let Err(e) = manager.send_data(data) {
+                eprintln!("Error sending data: {e}");
             } else {
-                eprintln!("Port is not open, cannot send data.");
+                return true;
             }
         } else {
-            eprintln!("Serial manager is not initialized.");
+            eprintln!("Port is not open, cannot send data.");
         }
+    } else {
+        eprintln!("Serial manager is not initialized.");
     }
+    false
+}
+
+/// Compact menu button to pick the checksum appended to a frame.
+pub fn trailer_menu(ui: &mut egui::Ui, trailer: &mut Trailer, id_salt: impl std::hash::Hash) {
+    let label = if trailer.is_enabled() {
+        trailer.kind.to_string()
+    } else {
+        "No checksum".to_string()
+    };
+
+    ui.push_id(id_salt, |ui| {
+        ui.menu_button(label, |ui| {
+            egui::Grid::new("trailer_grid")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Checksum:");
+                    egui::ComboBox::from_id_salt("trailer_kind_combo")
+                        .selected_text(trailer.kind.to_string())
+                        .show_ui(ui, |ui| {
+                            for kind in ChecksumKind::ALL {
+                                if ui
+                                    .selectable_value(&mut trailer.kind, kind, kind.to_string())
+                                    .changed()
+                                {
+                                    trailer.byte_order = kind.usual_byte_order();
+                                }
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Byte order:");
+                    ui.horizontal(|ui| {
+                        for order in [ByteOrder::BigEndian, ByteOrder::LittleEndian] {
+                            ui.radio_value(&mut trailer.byte_order, order, order.to_string());
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Skip first:");
+                    ui.add(egui::DragValue::new(&mut trailer.skip_start).suffix(" bytes"));
+                    ui.end_row();
+
+                    ui.label("Skip last:");
+                    ui.add(egui::DragValue::new(&mut trailer.skip_end).suffix(" bytes"));
+                    ui.end_row();
+                });
+        });
+    });
 }
 
 impl Default for SendPanel {
@@ -1,10 +1,39 @@
+use crate::chart::parser::{ChartParser, ParserConfig};
+use crate::checksum::ByteOrder;
+use crate::framing::{FrameSplitter, FramingMode, FramingSettings};
 use crate::generalsettings::AppSettings;
 
-pub struct SettingsPanel {}
+/// Error message of the last validated settings, so the parser regex and
+/// the framer are only rebuilt when their settings change.
+type Validation<T> = Option<(T, Option<String>)>;
+
+pub struct SettingsPanel {
+    parser_check: Validation<ParserConfig>,
+    framing_check: Validation<FramingSettings>,
+}
 
 impl SettingsPanel {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            parser_check: None,
+            framing_check: None,
+        }
+    }
+
+    /// Run `check` when `config` differs from the last validated one, and
+    /// return its cached error.
+    fn validate<'a, T: Clone + PartialEq>(
+        cache: &'a mut Validation<T>,
+        config: &T,
+        check: impl FnOnce(&T) -> Option<String>,
+    ) -> Option<&'a String> {
+        if cache
+            .as_ref()
+            .map_or(true, |(checked, _)| checked != config)
+        {
+            *cache = Some((config.clone(), check(config)));
+        }
+        cache.as_ref().and_then(|(_, err)| err.as_ref())
     }
 
     pub fn show(&mut self, ui: &mut egui::Ui, settings: &mut AppSettings) {
@@ -39,9 +68,157 @@ impl SettingsPanel {
                 ui.add(egui::DragValue::new(&mut settings.repaint_interval_ms).range(16..=1000));
             });
 
+            ui.horizontal(|ui| {
+                ui.label("Send history size:");
+                ui.add(egui::DragValue::new(&mut settings.send_history_size).range(1..=1000));
+            });
+
             ui.checkbox(&mut settings.byte_mode, "Byte mode");
             ui.checkbox(&mut settings.show_chart_panel, "Show chart panel");
             ui.checkbox(&mut settings.show_text_panel, "Show text panel");
+
+            ui.separator();
+            ui.label("Framing");
+            Self::framing(ui, &mut settings.framing);
+            if let Some(err) = Self::validate(&mut self.framing_check, &settings.framing, |framing| {
+                FrameSplitter::new(framing).err()
+            }) {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+            }
+
+            ui.separator();
+            ui.label("Chart");
+            ui.horizontal(|ui| {
+                ui.label("Buffer size:");
+                ui.add(
+                    egui::DragValue::new(&mut settings.chart_buffer_size)
+                        .range(10..=1_000_000)
+                        .suffix(" samples"),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Value delimiters:");
+                ui.text_edit_singleline(&mut settings.chart_value_delimiters)
+                    .on_hover_text("Any of these characters separates values. Escapes such as \\t are allowed.");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Record delimiter:");
+                ui.text_edit_singleline(&mut settings.chart_record_delimiter)
+                    .on_hover_text("Sequence ending each record, e.g. \\n or \\r\\n.");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Number regex:");
+                ui.text_edit_singleline(&mut settings.chart_number_regex)
+                    .on_hover_text(
+                        "Optional. In Columns format, the values of a record are the matches \
+                         of this pattern (or its first group), e.g. -?\\d+(?:\\.\\d+)?",
+                    );
+            });
+            let config = ParserConfig::from_settings(settings);
+            if let Some(err) = Self::validate(&mut self.parser_check, &config, |config| {
+                ChartParser::new(config).err()
+            }) {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+            }
+        });
+    }
+
+    /// Rule grouping received chunks into packets.
+    fn framing(ui: &mut egui::Ui, framing: &mut FramingSettings) {
+        ui.horizontal(|ui| {
+            ui.label("Packets:");
+            egui::ComboBox::from_id_salt("framing_mode")
+                .selected_text(framing.mode.to_string())
+                .show_ui(ui, |ui| {
+                    for mode in FramingMode::ALL {
+                        ui.selectable_value(&mut framing.mode, mode, mode.to_string());
+                    }
+                });
         });
+
+        match framing.mode {
+            FramingMode::None => {}
+            FramingMode::Delimiter => {
+                ui.horizontal(|ui| {
+                    ui.label("Delimiter:");
+                    ui.text_edit_singleline(&mut framing.delimiter)
+                        .on_hover_text("Sequence ending each packet, e.g. \\n or \\x03.");
+                });
+            }
+            FramingMode::FixedLength => {
+                ui.horizontal(|ui| {
+                    ui.label("Length:");
+                    ui.add(
+                        egui::DragValue::new(&mut framing.frame_length)
+                            .range(1..=65536)
+                            .suffix(" bytes"),
+                    );
+                });
+            }
+            FramingMode::LengthField => {
+                ui.horizontal(|ui| {
+                    ui.label("Field offset:");
+                    ui.add(egui::DragValue::new(&mut framing.length_offset).range(0..=1024));
+                    ui.label("Size:");
+                    egui::ComboBox::from_id_salt("framing_length_size")
+                        .width(40.0)
+                        .selected_text(framing.length_size.to_string())
+                        .show_ui(ui, |ui| {
+                            for size in [1, 2, 4] {
+                                ui.selectable_value(
+                                    &mut framing.length_size,
+                                    size,
+                                    size.to_string(),
+                                );
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Byte order:");
+                    egui::ComboBox::from_id_salt("framing_byte_order")
+                        .selected_text(framing.length_byte_order.to_string())
+                        .show_ui(ui, |ui| {
+                            for order in [ByteOrder::BigEndian, ByteOrder::LittleEndian] {
+                                ui.selectable_value(
+                                    &mut framing.length_byte_order,
+                                    order,
+                                    order.to_string(),
+                                );
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Length adjust:");
+                    ui.add(egui::DragValue::new(&mut framing.length_adjust).range(-1024..=1024))
+                        .on_hover_text(
+                            "Added to the field value to get the bytes following the field, \
+                             e.g. 2 for a trailing CRC or minus the header size when the field \
+                             counts the whole packet.",
+                        );
+                });
+            }
+            FramingMode::IdleGap => {
+                ui.horizontal(|ui| {
+                    ui.label("Gap:");
+                    ui.add(
+                        egui::DragValue::new(&mut framing.idle_gap_ms)
+                            .range(1..=10_000)
+                            .suffix(" ms"),
+                    )
+                    .on_hover_text("A packet ends when no byte arrived for this long.");
+                });
+            }
+        }
+
+        if framing.mode != FramingMode::None {
+            ui.horizontal(|ui| {
+                ui.label("Max packet length:");
+                ui.add(
+                    egui::DragValue::new(&mut framing.max_frame_length)
+                        .range(1..=1_000_000)
+                        .suffix(" bytes"),
+                );
+            });
+        }
     }
 }
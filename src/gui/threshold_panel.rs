@@ -0,0 +1,135 @@
+use crate::chart::alarm::{Threshold, ThresholdCondition};
+use crate::macros::Macro;
+
+/// Editor for the limit lines of the chart series and their alarm actions.
+pub struct ThresholdPanel {
+    pub open: bool,
+}
+
+impl ThresholdPanel {
+    pub fn new() -> Self {
+        Self { open: false }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        thresholds: &mut Vec<Threshold>,
+        series_names: &[String],
+        macros: &[Macro],
+    ) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Thresholds and alarms")
+            .open(&mut open)
+            .default_size([700.0, 250.0])
+            .show(ctx, |ui| {
+                let mut remove = None;
+                egui::ScrollArea::vertical()
+                    .max_height(250.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("thresholds_grid")
+                            .striped(true)
+                            .num_columns(9)
+                            .show(ui, |ui| {
+                                for header in [
+                                    "Series",
+                                    "Alarm when",
+                                    "Level",
+                                    "Highlight",
+                                    "Pause",
+                                    "Log",
+                                    "Sound",
+                                    "Send macro",
+                                    "",
+                                ] {
+                                    ui.strong(header);
+                                }
+                                ui.end_row();
+
+                                for (index, threshold) in thresholds.iter_mut().enumerate() {
+                                    Self::threshold_row(ui, index, threshold, series_names, macros);
+                                    if ui.button("🗑").clicked() {
+                                        remove = Some(index);
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                if let Some(index) = remove {
+                    thresholds.remove(index);
+                }
+
+                if ui.button("Add threshold").clicked() {
+                    thresholds.push(Threshold {
+                        series: series_names.first().cloned().unwrap_or_default(),
+                        ..Threshold::default()
+                    });
+                }
+                ui.weak(
+                    "An alarm is raised when a value crosses the level and again only after \
+                     the series came back.",
+                );
+            });
+        self.open = open;
+    }
+
+    fn threshold_row(
+        ui: &mut egui::Ui,
+        index: usize,
+        threshold: &mut Threshold,
+        series_names: &[String],
+        macros: &[Macro],
+    ) {
+        egui::ComboBox::from_id_salt(("threshold_series", index))
+            .selected_text(&threshold.series)
+            .show_ui(ui, |ui| {
+                for name in series_names {
+                    ui.selectable_value(&mut threshold.series, name.clone(), name);
+                }
+            });
+
+        egui::ComboBox::from_id_salt(("threshold_condition", index))
+            .width(60.0)
+            .selected_text(threshold.condition.to_string())
+            .show_ui(ui, |ui| {
+                for condition in ThresholdCondition::ALL {
+                    ui.selectable_value(&mut threshold.condition, condition, condition.to_string());
+                }
+            });
+
+        ui.add(egui::DragValue::new(&mut threshold.level).speed(0.1));
+        ui.checkbox(&mut threshold.highlight, "");
+        ui.checkbox(&mut threshold.pause, "");
+        ui.checkbox(&mut threshold.log, "");
+        ui.checkbox(&mut threshold.sound, "");
+
+        let selected = if threshold.send_macro.is_empty() {
+            "None".to_string()
+        } else {
+            threshold.send_macro.clone()
+        };
+        egui::ComboBox::from_id_salt(("threshold_macro", index))
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut threshold.send_macro, String::new(), "None");
+                for macro_def in macros {
+                    ui.selectable_value(
+                        &mut threshold.send_macro,
+                        macro_def.name.clone(),
+                        &macro_def.name,
+                    );
+                }
+            });
+    }
+}
+
+impl Default for ThresholdPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
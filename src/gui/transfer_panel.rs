@@ -0,0 +1,387 @@
+use super::send_panel::send_to_manager;
+use crate::communicationtrait::CommunicationManager;
+use crate::transfer::zmodem::{self, ZRQINIT_SEQUENCE};
+use crate::transfer::{
+    FileTransfer, TransferProtocol, TransferRunner, TransferState, TransferStatus, XmodemReceiver,
+    XmodemSender, ZmodemReceiver, ZmodemSender,
+};
+use std::time::{Duration, Instant};
+
+/// How long after a transfer the bytes of its trailer are expected.
+const TRAILER_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum TransferDirection {
+    Send,
+    Receive,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TransferPanel {
+    pub protocol: TransferProtocol,
+    pub direction: TransferDirection,
+    pub send_path: String,
+    pub receive_path: String,
+    /// ZMODEM send: type `rz` first to start the receiver on a shell.
+    pub start_remote_rz: bool,
+
+    #[serde(skip)]
+    pub open: bool,
+    /// A ZMODEM sender was detected and is waiting for the user to accept.
+    #[serde(skip)]
+    incoming_zmodem: bool,
+    /// Last bytes of the previous chunk, for sequences split across reads.
+    #[serde(skip)]
+    detect_tail: Vec<u8>,
+    #[serde(skip)]
+    transfer: Option<TransferRunner>,
+    /// Rest of the finished transfer's trailer and when to stop expecting it.
+    #[serde(skip)]
+    trailer: Option<(Vec<u8>, Instant)>,
+    #[serde(skip)]
+    error: Option<String>,
+}
+
+impl TransferPanel {
+    pub fn new() -> Self {
+        Self {
+            protocol: TransferProtocol::XmodemCrc,
+            direction: TransferDirection::Send,
+            send_path: String::new(),
+            receive_path: String::new(),
+            start_remote_rz: true,
+            open: false,
+            incoming_zmodem: false,
+            detect_tail: Vec::new(),
+            transfer: None,
+            trailer: None,
+            error: None,
+        }
+    }
+
+    /// True while a transfer, or a ZMODEM offer waiting for an answer, owns
+    /// the incoming byte stream.
+    pub fn is_active(&self) -> bool {
+        self.incoming_zmodem
+            || self
+                .transfer
+                .as_ref()
+                .is_some_and(|transfer| !transfer.is_finished())
+    }
+
+    /// Remove what is left of a finished transfer's trailer, e.g. the "OO"
+    /// closing a ZMODEM session, from the start of `data`.
+    pub fn strip_trailer(&mut self, mut data: Vec<u8>) -> Vec<u8> {
+        let Some((trailer, deadline)) = &mut self.trailer else {
+            return data;
+        };
+        if Instant::now() > *deadline {
+            self.trailer = None;
+            return data;
+        }
+
+        let matched = data
+            .iter()
+            .zip(trailer.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        if matched == data.len() && matched < trailer.len() {
+            // The rest may come in the next chunk
+            trailer.drain(..matched);
+        } else {
+            self.trailer = None;
+        }
+        data.drain(..matched);
+        data
+    }
+
+    /// Look for the ZRQINIT header a ZMODEM sender (`sz`) starts with. When
+    /// found, the transfer dialog opens and asks whether to receive, and the
+    /// position of the header in `data` is returned. The bytes before it are
+    /// ordinary received data.
+    pub fn detect_incoming(&mut self, data: &[u8]) -> Option<usize> {
+        let mut window = std::mem::take(&mut self.detect_tail);
+        let tail_length = window.len();
+        window.extend_from_slice(data);
+
+        let found = window
+            .windows(ZRQINIT_SEQUENCE.len())
+            .position(|candidate| candidate == ZRQINIT_SEQUENCE);
+        match found {
+            Some(position) => {
+                self.incoming_zmodem = true;
+                self.open = true;
+                self.error = None;
+                // A header started in the previous chunk begins this one
+                Some(position.saturating_sub(tail_length))
+            }
+            None => {
+                let keep = window.len().min(ZRQINIT_SEQUENCE.len() - 1);
+                self.detect_tail = window.split_off(window.len() - keep);
+                None
+            }
+        }
+    }
+
+    /// Feed received bytes to the running transfer.
+    pub fn handle_data(&mut self, data: &[u8]) {
+        if let Some(transfer) = &self.transfer {
+            transfer.on_data(data);
+            if transfer.is_finished() {
+                let trailer = transfer.trailer();
+                if !trailer.is_empty() {
+                    self.trailer = Some((trailer, Instant::now() + TRAILER_TIMEOUT));
+                }
+            }
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        manager: &mut Option<Box<dyn CommunicationManager>>,
+    ) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("File transfer")
+            .open(&mut open)
+            .resizable(false)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                if self.incoming_zmodem {
+                    self.incoming_prompt(ui, manager);
+                    return;
+                }
+
+                let active = self.is_active();
+
+                ui.add_enabled_ui(!active, |ui| {
+                    egui::Grid::new("transfer_grid")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("Protocol:");
+                            egui::ComboBox::from_id_salt("transfer_protocol_combo")
+                                .selected_text(self.protocol.to_string())
+                                .show_ui(ui, |ui| {
+                                    for protocol in TransferProtocol::ALL {
+                                        ui.selectable_value(
+                                            &mut self.protocol,
+                                            protocol,
+                                            protocol.to_string(),
+                                        );
+                                    }
+                                });
+                            ui.end_row();
+
+                            ui.label("Direction:");
+                            ui.horizontal(|ui| {
+                                ui.radio_value(
+                                    &mut self.direction,
+                                    TransferDirection::Send,
+                                    "Send",
+                                );
+                                ui.radio_value(
+                                    &mut self.direction,
+                                    TransferDirection::Receive,
+                                    "Receive",
+                                );
+                            });
+                            ui.end_row();
+
+                            match self.direction {
+                                TransferDirection::Send => {
+                                    ui.label("File:");
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut self.send_path)
+                                            .desired_width(320.0),
+                                    );
+                                }
+                                TransferDirection::Receive => {
+                                    if self.protocol.is_batch() {
+                                        ui.label("Save to folder:");
+                                    } else {
+                                        ui.label("Save as:");
+                                    }
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut self.receive_path)
+                                            .desired_width(320.0),
+                                    );
+                                }
+                            }
+                            ui.end_row();
+
+                            if self.protocol == TransferProtocol::Zmodem
+                                && self.direction == TransferDirection::Send
+                            {
+                                ui.label("");
+                                ui.checkbox(
+                                    &mut self.start_remote_rz,
+                                    "Type \"rz\" to start receiver",
+                                );
+                                ui.end_row();
+                            }
+                        });
+                });
+
+                ui.horizontal(|ui| {
+                    if active {
+                        if ui.button("Cancel").clicked() {
+                            if let Some(transfer) = &self.transfer {
+                                transfer.cancel();
+                            }
+                        }
+                    } else if ui.button("Start").clicked() {
+                        self.start(ctx, manager);
+                    }
+                });
+
+                if let Some(status) = self.transfer.as_ref().and_then(TransferRunner::status) {
+                    Self::progress(ui, &status);
+                }
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+            });
+        self.open = open;
+
+        // Closing the dialog answers a pending ZMODEM offer with "no"
+        if !self.open && self.incoming_zmodem {
+            self.incoming_zmodem = false;
+            send_to_manager(manager, zmodem::cancel_sequence());
+        }
+    }
+
+    fn start(&mut self, ctx: &egui::Context, manager: &Option<Box<dyn CommunicationManager>>) {
+        self.error = None;
+        if !manager.as_ref().is_some_and(|manager| manager.is_running()) {
+            self.error = Some("Port is not open".to_string());
+            return;
+        }
+
+        let transfer: Box<dyn FileTransfer> = match (self.direction, self.protocol.xmodem_variant())
+        {
+            (TransferDirection::Send, variant) => {
+                let sender: std::io::Result<Box<dyn FileTransfer>> = match variant {
+                    Some(variant) => XmodemSender::from_file(variant, &self.send_path)
+                        .map(|sender| Box::new(sender) as Box<dyn FileTransfer>),
+                    None => ZmodemSender::from_file(&self.send_path, self.start_remote_rz)
+                        .map(|sender| Box::new(sender) as Box<dyn FileTransfer>),
+                };
+                match sender {
+                    Ok(sender) => sender,
+                    Err(e) => {
+                        self.error = Some(format!("Cannot read \"{}\": {e}", self.send_path));
+                        return;
+                    }
+                }
+            }
+            (TransferDirection::Receive, Some(variant)) => {
+                Box::new(XmodemReceiver::new(variant, &self.receive_path))
+            }
+            (TransferDirection::Receive, None) => Box::new(ZmodemReceiver::new(&self.receive_path)),
+        };
+
+        self.run(ctx, transfer, manager);
+    }
+
+    fn run(
+        &mut self,
+        ctx: &egui::Context,
+        transfer: Box<dyn FileTransfer>,
+        manager: &Option<Box<dyn CommunicationManager>>,
+    ) {
+        let Some(sender) = manager.as_ref().and_then(|manager| manager.data_sender()) else {
+            self.error = Some("Port is not open".to_string());
+            return;
+        };
+        self.trailer = None;
+        self.transfer = Some(TransferRunner::start(transfer, sender, ctx.clone()));
+    }
+
+    fn incoming_prompt(
+        &mut self,
+        ui: &mut egui::Ui,
+        manager: &mut Option<Box<dyn CommunicationManager>>,
+    ) {
+        ui.label("The device started a ZMODEM upload.");
+        ui.horizontal(|ui| {
+            ui.label("Save to folder:");
+            ui.add(egui::TextEdit::singleline(&mut self.receive_path).desired_width(300.0));
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Receive").clicked() {
+                self.incoming_zmodem = false;
+                self.protocol = TransferProtocol::Zmodem;
+                self.direction = TransferDirection::Receive;
+                let receiver = Box::new(ZmodemReceiver::new(&self.receive_path));
+                self.run(ui.ctx(), receiver, manager);
+            }
+            if ui.button("Decline").clicked() {
+                self.incoming_zmodem = false;
+                send_to_manager(manager, zmodem::cancel_sequence());
+            }
+        });
+    }
+
+    fn progress(ui: &mut egui::Ui, status: &TransferStatus) {
+        if let Some(total) = status.total_bytes.filter(|&total| total > 0) {
+            ui.add(egui::ProgressBar::new(status.bytes as f32 / total as f32).show_percentage());
+        }
+
+        egui::Grid::new("transfer_status_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("File:");
+                ui.label(&status.file_name);
+                ui.end_row();
+
+                ui.label("Blocks:");
+                ui.label(status.blocks.to_string());
+                ui.end_row();
+
+                ui.label("Bytes:");
+                match status.total_bytes {
+                    Some(total) => ui.label(format!("{} / {total}", status.bytes)),
+                    None => ui.label(status.bytes.to_string()),
+                };
+                ui.end_row();
+
+                ui.label("Retries:");
+                ui.label(status.retries.to_string());
+                ui.end_row();
+
+                ui.label("Errors:");
+                ui.label(status.errors.to_string());
+                ui.end_row();
+            });
+
+        match &status.state {
+            TransferState::Running => {
+                ui.label("Transferring...");
+            }
+            TransferState::Done => {
+                ui.label("Transfer complete");
+            }
+            TransferState::Cancelled => {
+                ui.label("Transfer cancelled");
+            }
+            TransferState::Failed(err) => {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+            }
+        }
+        if let Some(last_error) = &status.last_error {
+            ui.label(format!("Last error: {last_error}"));
+        }
+    }
+}
+
+impl Default for TransferPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,11 +1,20 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
+mod beep;
+mod chart;
+mod checksum;
 mod communicationtrait;
+mod file_sender;
+mod framing;
 mod generalsettings;
 mod gui;
 mod guistrings;
 mod info;
+mod macros;
+mod payload;
+mod repeat_sender;
 mod serial_impl;
+mod transfer;
 mod update;
 pub use app::TemplateApp;
@@ -0,0 +1,85 @@
+use crate::checksum::Trailer;
+use crate::payload::{self, LineEnding, PayloadMode};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Result};
+
+/// A named command that can be sent with one click or a keyboard shortcut.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Macro {
+    pub name: String,
+    pub payload: String,
+    pub mode: PayloadMode,
+    pub line_ending: LineEnding,
+    /// Checksum appended to the payload, before the line ending.
+    pub trailer: Trailer,
+    /// Shortcut such as `F5` or `Ctrl+Shift+1`. Empty means no shortcut.
+    pub shortcut: String,
+}
+
+impl Default for Macro {
+    fn default() -> Self {
+        Self {
+            name: "New macro".to_string(),
+            payload: String::new(),
+            mode: PayloadMode::Text,
+            line_ending: LineEnding::None,
+            trailer: Trailer::default(),
+            shortcut: String::new(),
+        }
+    }
+}
+
+impl Macro {
+    /// Bytes to put on the wire: the encoded payload, its checksum and the
+    /// line ending.
+    pub fn to_bytes(&self) -> std::result::Result<Vec<u8>, String> {
+        let mut data = payload::encode(&self.payload, self.mode)?;
+        self.trailer.append(&mut data)?;
+        data.extend_from_slice(self.line_ending.as_bytes());
+        Ok(data)
+    }
+
+    /// Parse [`Macro::shortcut`]. Returns `None` if it is empty or invalid.
+    pub fn keyboard_shortcut(&self) -> Option<egui::KeyboardShortcut> {
+        parse_shortcut(&self.shortcut)
+    }
+}
+
+/// Parse a shortcut like `Ctrl+Alt+F1`. `Ctrl` and `Cmd` both map to the
+/// platform command key.
+pub fn parse_shortcut(text: &str) -> Option<egui::KeyboardShortcut> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+
+    let mut modifiers = egui::Modifiers::NONE;
+    let mut parts = text.split('+').map(str::trim).peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            let key = egui::Key::from_name(part)?;
+            return Some(egui::KeyboardShortcut::new(modifiers, key));
+        }
+        modifiers |= match part.to_lowercase().as_str() {
+            "ctrl" | "cmd" | "command" => egui::Modifiers::COMMAND,
+            "alt" | "option" => egui::Modifiers::ALT,
+            "shift" => egui::Modifiers::SHIFT,
+            _ => return None,
+        };
+    }
+    None
+}
+
+/// Write macros to a JSON file that can be shared and imported elsewhere.
+pub fn export_macros(path: &str, macros: &[Macro]) -> Result<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(writer, macros).map_err(std::io::Error::other)
+}
+
+/// Read macros previously written by [`export_macros`].
+pub fn import_macros(path: &str) -> Result<Vec<Macro>> {
+    let reader = BufReader::new(File::open(path)?);
+    serde_json::from_reader(reader).map_err(std::io::Error::other)
+}
@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+
+/// How the text typed by the user is turned into bytes before sending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PayloadMode {
+    /// Send the text as UTF-8, unchanged.
+    #[default]
+    Text,
+    /// Hex byte string, e.g. `01 03 00 00 00 0A C5 CD`.
+    Hex,
+    /// Text with C-style escapes, e.g. `AT\r\n` or `\x1B[2J`.
+    Escaped,
+}
+
+impl PayloadMode {
+    pub const ALL: [PayloadMode; 3] = [PayloadMode::Text, PayloadMode::Hex, PayloadMode::Escaped];
+}
+
+impl std::fmt::Display for PayloadMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PayloadMode::Text => write!(f, "Text"),
+            PayloadMode::Hex => write!(f, "Hex"),
+            PayloadMode::Escaped => write!(f, "Escapes"),
+        }
+    }
+}
+
+/// Convert `input` to the bytes that should go on the wire for the given mode.
+pub fn encode(input: &str, mode: PayloadMode) -> Result<Vec<u8>, String> {
+    match mode {
+        PayloadMode::Text => Ok(input.as_bytes().to_vec()),
+        PayloadMode::Hex => parse_hex(input),
+        PayloadMode::Escaped => parse_escaped(input),
+    }
+}
+
+/// Parse whitespace or comma separated hex bytes. Each group may hold several
+/// bytes (`0103`) and may carry a `0x` prefix.
+pub fn parse_hex(input: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+
+    for group in input.split(|c: char| c.is_whitespace() || c == ',') {
+        if group.is_empty() {
+            continue;
+        }
+        let prefixed = group
+            .strip_prefix("0x")
+            .or_else(|| group.strip_prefix("0X"));
+        let digits = prefixed.unwrap_or(group);
+
+        if let Some(bad) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid hex digit '{bad}' in \"{group}\""));
+        }
+        if prefixed.is_some() && digits.is_empty() {
+            return Err(format!("Missing digits after 0x in \"{group}\""));
+        }
+        if digits.len() % 2 != 0 {
+            return Err(format!("Odd number of hex digits in \"{group}\""));
+        }
+
+        let mut nibbles = digits.chars().filter_map(|c| c.to_digit(16));
+        while let (Some(hi), Some(lo)) = (nibbles.next(), nibbles.next()) {
+            bytes.push((hi * 16 + lo) as u8);
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Expand C-style escapes: `\r`, `\n`, `\t`, `\0`, `\\`, `\"`, `\'` and `\xHH`.
+pub fn parse_escaped(input: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut chars = input.char_indices();
+
+    while let Some((pos, ch)) = chars.next() {
+        if ch != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+
+        match chars.next() {
+            Some((_, 'r')) => bytes.push(b'\r'),
+            Some((_, 'n')) => bytes.push(b'\n'),
+            Some((_, 't')) => bytes.push(b'\t'),
+            Some((_, '0')) => bytes.push(0),
+            Some((_, '\\')) => bytes.push(b'\\'),
+            Some((_, '"')) => bytes.push(b'"'),
+            Some((_, '\'')) => bytes.push(b'\''),
+            Some((_, 'x')) => {
+                let mut hex_digit = || chars.next().and_then(|(_, c)| c.to_digit(16));
+                match (hex_digit(), hex_digit()) {
+                    (Some(hi), Some(lo)) => bytes.push((hi * 16 + lo) as u8),
+                    _ => {
+                        return Err(format!(
+                            "\\x at position {pos} must be followed by two hex digits"
+                        ))
+                    }
+                }
+            }
+            Some((_, other)) => {
+                return Err(format!("Unknown escape '\\{other}' at position {pos}"));
+            }
+            None => return Err("Trailing '\\' at end of input".to_string()),
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Line terminator appended after the encoded payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LineEnding {
+    #[default]
+    None,
+    Lf,
+    Cr,
+    CrLf,
+}
+
+impl LineEnding {
+    pub const ALL: [LineEnding; 4] = [
+        LineEnding::None,
+        LineEnding::Lf,
+        LineEnding::Cr,
+        LineEnding::CrLf,
+    ];
+
+    pub fn as_bytes(&self) -> &'static [u8] {
+        match self {
+            LineEnding::None => b"",
+            LineEnding::Lf => b"\n",
+            LineEnding::Cr => b"\r",
+            LineEnding::CrLf => b"\r\n",
+        }
+    }
+}
+
+impl std::fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LineEnding::None => write!(f, "None"),
+            LineEnding::Lf => write!(f, "LF"),
+            LineEnding::Cr => write!(f, "CR"),
+            LineEnding::CrLf => write!(f, "CR+LF"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_separators() {
+        assert_eq!(parse_hex("01 02,03\t04\n05"), Ok(vec![1, 2, 3, 4, 5]));
+        assert_eq!(parse_hex(" 0a0B , ,ff "), Ok(vec![0x0A, 0x0B, 0xFF]));
+        assert_eq!(parse_hex(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn hex_prefix() {
+        assert_eq!(parse_hex("0x01 0XAbCd"), Ok(vec![0x01, 0xAB, 0xCD]));
+        assert_eq!(
+            parse_hex("0x"),
+            Err("Missing digits after 0x in \"0x\"".to_string())
+        );
+        assert_eq!(
+            parse_hex("01 0X"),
+            Err("Missing digits after 0x in \"0X\"".to_string())
+        );
+    }
+
+    #[test]
+    fn hex_errors() {
+        assert_eq!(
+            parse_hex("01 123"),
+            Err("Odd number of hex digits in \"123\"".to_string())
+        );
+        assert_eq!(
+            parse_hex("0x1"),
+            Err("Odd number of hex digits in \"0x1\"".to_string())
+        );
+        assert_eq!(
+            parse_hex("0g"),
+            Err("Invalid hex digit 'g' in \"0g\"".to_string())
+        );
+        assert!(parse_hex("01-02").is_err());
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(parse_escaped("AT\\r\\n"), Ok(b"AT\r\n".to_vec()));
+        assert_eq!(parse_escaped("a\\tb"), Ok(b"a\tb".to_vec()));
+        assert_eq!(parse_escaped("\\\\"), Ok(b"\\".to_vec()));
+        assert_eq!(parse_escaped("\\0\\\"\\'"), Ok(b"\0\"'".to_vec()));
+        assert_eq!(parse_escaped("\\x1B[2J\\xff"), Ok(b"\x1B[2J\xFF".to_vec()));
+        assert_eq!(parse_escaped("é"), Ok("é".as_bytes().to_vec()));
+    }
+
+    #[test]
+    fn escape_errors() {
+        assert!(parse_escaped("\\x").is_err());
+        assert!(parse_escaped("\\x1").is_err());
+        assert!(parse_escaped("\\xG0").is_err());
+        assert!(parse_escaped("\\q").is_err());
+        assert_eq!(
+            parse_escaped("ab\\"),
+            Err("Trailing '\\' at end of input".to_string())
+        );
+    }
+}
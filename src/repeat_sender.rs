@@ -0,0 +1,97 @@
+use crate::communicationtrait::DataSender;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Longest time the worker sleeps before checking for a stop request.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Sends the same data at a fixed interval on a background thread, so the
+/// timing does not depend on how often the GUI repaints.
+pub struct RepeatSender {
+    stop: Arc<AtomicBool>,
+    running: Arc<AtomicBool>,
+    sent: Arc<AtomicU64>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl RepeatSender {
+    /// Start sending `data` every `interval`. With `limit` set, stop after
+    /// that many messages. `ctx` is repainted after every send so counters
+    /// shown in the GUI stay current.
+    pub fn start(
+        sender: DataSender,
+        data: Vec<u8>,
+        interval: Duration,
+        limit: Option<u64>,
+        ctx: egui::Context,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let running = Arc::new(AtomicBool::new(true));
+        let sent = Arc::new(AtomicU64::new(0));
+
+        let stop_clone = Arc::clone(&stop);
+        let running_clone = Arc::clone(&running);
+        let sent_clone = Arc::clone(&sent);
+        let handle = thread::spawn(move || {
+            let mut next_send = Instant::now();
+            while !stop_clone.load(Ordering::Relaxed) {
+                if limit.is_some_and(|limit| sent_clone.load(Ordering::Relaxed) >= limit) {
+                    break;
+                }
+
+                let now = Instant::now();
+                if now < next_send {
+                    thread::sleep((next_send - now).min(STOP_POLL_INTERVAL));
+                    continue;
+                }
+
+                if sender.send(data.clone()).is_err() {
+                    eprintln!("Port closed, stopping periodic send");
+                    break;
+                }
+                sent_clone.fetch_add(1, Ordering::Relaxed);
+                ctx.request_repaint();
+
+                next_send += interval;
+                // Don't try to catch up after a stall, just resume the cadence
+                if next_send < now {
+                    next_send = now + interval;
+                }
+            }
+            running_clone.store(false, Ordering::Relaxed);
+            ctx.request_repaint();
+        });
+
+        Self {
+            stop,
+            running,
+            sent,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                eprintln!("Periodic send thread panicked");
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    pub fn sent_count(&self) -> u64 {
+        self.sent.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for RepeatSender {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -93,6 +93,17 @@ pub struct PortSettings {
     pub flowcontrol: FlowControl,
     pub parity: Parity,
     pub stop_bits: StopBits,
+    /// Pause after every transmitted byte, in milliseconds.
+    pub char_delay_ms: u64,
+    /// Extra pause after every transmitted `\n`, in milliseconds.
+    pub line_delay_ms: u64,
+}
+
+impl PortSettings {
+    /// True if outgoing data has to be written byte by byte.
+    pub fn is_paced(&self) -> bool {
+        self.char_delay_ms > 0 || self.line_delay_ms > 0
+    }
 }
 
 impl Default for PortSettings {
@@ -103,6 +114,8 @@ impl Default for PortSettings {
             flowcontrol: FlowControl::None,
             parity: Parity::None,
             stop_bits: StopBits::One,
+            char_delay_ms: 0,
+            line_delay_ms: 0,
         }
     }
 }
@@ -115,6 +128,8 @@ impl Clone for PortSettings {
             flowcontrol: self.flowcontrol,
             parity: self.parity,
             stop_bits: self.stop_bits,
+            char_delay_ms: self.char_delay_ms,
+            line_delay_ms: self.line_delay_ms,
         }
     }
 }
@@ -1,19 +1,26 @@
 use serialport::{available_ports, SerialPort, SerialPortType};
+use std::collections::VecDeque;
 use std::io::Result;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{sync::mpsc, thread};
 
 use crate::{
-    communicationtrait::{CommunicationEvent, CommunicationManager, EPortState},
+    communicationtrait::{
+        CommunicationEvent, CommunicationManager, DataSender, EPortState, QueuedData, WriteProgress,
+    },
     serial_impl::PortSettings,
 };
 
+/// Longest the port thread waits for data to send before it checks for
+/// received bytes again.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
 pub struct SerialCommunication {
     port_settings: PortSettings,
     port_state: Arc<Mutex<EPortState>>,
     port_thread: Option<thread::JoinHandle<()>>,
-    tx_to_serial: Option<mpsc::Sender<Vec<u8>>>,
+    tx_to_serial: Option<DataSender>,
 }
 impl SerialCommunication {
     pub(crate) fn new() -> Self {
@@ -33,8 +40,9 @@ impl CommunicationManager for SerialCommunication {
         }
         let port_settings_clone = self.port_settings.clone();
         let port_state_clone = Arc::clone(&self.port_state);
-        let (tx_to_serial, rx_from_app) = mpsc::channel();
-        self.tx_to_serial = Some(tx_to_serial);
+        let (tx_to_serial, rx_from_app) = mpsc::channel::<QueuedData>();
+        let progress = Arc::new(WriteProgress::default());
+        self.tx_to_serial = Some(DataSender::new(tx_to_serial, Arc::clone(&progress)));
         let handle = thread::spawn(move || {
             // some work here
             let mut port: Option<Box<dyn SerialPort>>;
@@ -61,21 +69,35 @@ impl CommunicationManager for SerialCommunication {
                             port_settings_clone.port_name, e
                         );
                         *port_state = EPortState::Closed;
+                        progress.close();
                         return;
                     }
                 }
             }
 
+            let char_delay = Duration::from_millis(port_settings_clone.char_delay_ms);
+            let line_delay = Duration::from_millis(port_settings_clone.line_delay_ms);
+            // Messages taken from the queue and not yet written, with the
+            // queue position of their first unwritten byte
+            let mut pending: VecDeque<QueuedData> = VecDeque::new();
+            let mut next_write = Instant::now();
+
             while *port_state_clone.lock().unwrap() == EPortState::Open {
+                // Whether bytes were read or written in this round
+                let mut busy = false;
                 if let Some(ref mut port_instance) = port {
                     let size = port_instance.bytes_to_read().unwrap_or(0);
                     if size > 0 {
+                        busy = true;
                         let mut serial_buf: Vec<u8> = vec![0; size as usize];
                         match port_instance.read_exact(&mut serial_buf) {
                             Ok(_) => {
                                 // Handle channel send errors gracefully
                                 if tx
-                                    .send(CommunicationEvent::DataReceived(serial_buf))
+                                    .send(CommunicationEvent::DataReceived(
+                                        serial_buf,
+                                        Instant::now(),
+                                    ))
                                     .is_err()
                                 {
                                     eprintln!("GUI channel disconnected, stopping serial thread");
@@ -89,7 +111,46 @@ impl CommunicationManager for SerialCommunication {
                     }
                 }
 
-                if let Ok(message) = rx_from_app.try_recv() {
+                let discard = progress.take_discard();
+                while let Ok(message) = rx_from_app.try_recv() {
+                    pending.push_back(message);
+                }
+                if !discard.is_empty() {
+                    // Drop what a sender withdrew, counting it as done
+                    pending.retain(|(position, message)| {
+                        let dropped = discard.iter().any(|range| range.contains(position));
+                        if dropped {
+                            progress.add_written(message.len());
+                        }
+                        !dropped
+                    });
+                }
+
+                if port_settings_clone.is_paced() {
+                    // Write one byte at a time, so the reads above keep
+                    // running between the pauses.
+                    let now = Instant::now();
+                    if now >= next_write {
+                        if let (Some(port_instance), Some((position, message))) =
+                            (port.as_mut(), pending.front_mut())
+                        {
+                            let byte = message.remove(0);
+                            *position += 1;
+                            if message.is_empty() {
+                                pending.pop_front();
+                            }
+                            if let Err(e) = port_instance.write_all(&[byte]) {
+                                eprintln!("{e:?}");
+                            }
+                            progress.add_written(1);
+                            busy = true;
+                            next_write = now + char_delay;
+                            if byte == b'\n' {
+                                next_write += line_delay;
+                            }
+                        }
+                    }
+                } else if let Some((_, message)) = pending.pop_front() {
                     // self.write_log(message.as_str());
                     if let Some(ref mut port_instance) = port {
                         match port_instance.write_all(&message) {
@@ -97,8 +158,23 @@ impl CommunicationManager for SerialCommunication {
                             Err(e) => eprintln!("{e:?}"),
                         }
                     }
+                    progress.add_written(message.len());
+                    busy = true;
+                }
+
+                if !busy {
+                    // Sleep until the next paced byte is due or data is queued,
+                    // waking up regularly to read
+                    let mut wait = IDLE_POLL_INTERVAL;
+                    if !pending.is_empty() {
+                        wait = wait.min(next_write.saturating_duration_since(Instant::now()));
+                    }
+                    if let Ok(message) = rx_from_app.recv_timeout(wait) {
+                        pending.push_back(message);
+                    }
                 }
             }
+            progress.close();
         });
         self.port_thread = Some(handle);
         Ok(())
@@ -136,6 +212,14 @@ impl CommunicationManager for SerialCommunication {
         Ok(())
     }
 
+    fn data_sender(&self) -> Option<DataSender> {
+        if self.is_running() {
+            self.tx_to_serial.clone()
+        } else {
+            None
+        }
+    }
+
     fn get_available_connections(&self) -> Vec<String> {
         let mut port_list: Vec<String> = vec![];
         match available_ports() {
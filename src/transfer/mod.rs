@@ -0,0 +1,173 @@
+pub mod runner;
+pub mod xmodem;
+pub mod zmodem;
+pub use runner::TransferRunner;
+pub use xmodem::{XmodemReceiver, XmodemSender, XmodemVariant};
+pub use zmodem::{ZmodemReceiver, ZmodemSender};
+
+use std::time::Instant;
+
+/// Protocols offered in the transfer dialog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum TransferProtocol {
+    Xmodem,
+    XmodemCrc,
+    Xmodem1k,
+    Ymodem,
+    Zmodem,
+}
+
+impl TransferProtocol {
+    pub const ALL: [TransferProtocol; 5] = [
+        TransferProtocol::Xmodem,
+        TransferProtocol::XmodemCrc,
+        TransferProtocol::Xmodem1k,
+        TransferProtocol::Ymodem,
+        TransferProtocol::Zmodem,
+    ];
+
+    /// The XMODEM flavour, or `None` for ZMODEM.
+    pub fn xmodem_variant(&self) -> Option<XmodemVariant> {
+        match self {
+            TransferProtocol::Xmodem => Some(XmodemVariant::Checksum),
+            TransferProtocol::XmodemCrc => Some(XmodemVariant::Crc),
+            TransferProtocol::Xmodem1k => Some(XmodemVariant::OneK),
+            TransferProtocol::Ymodem => Some(XmodemVariant::Ymodem),
+            TransferProtocol::Zmodem => None,
+        }
+    }
+
+    /// True if received files keep the name chosen by the sender.
+    pub fn is_batch(&self) -> bool {
+        matches!(self, TransferProtocol::Ymodem | TransferProtocol::Zmodem)
+    }
+}
+
+impl std::fmt::Display for TransferProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.xmodem_variant() {
+            Some(variant) => variant.fmt(f),
+            None => write!(f, "ZMODEM"),
+        }
+    }
+}
+
+/// Overall state of a file transfer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransferState {
+    Running,
+    Done,
+    Cancelled,
+    Failed(String),
+}
+
+/// Counters shown in the transfer dialog.
+#[derive(Debug, Clone)]
+pub struct TransferStatus {
+    pub state: TransferState,
+    pub file_name: String,
+    pub blocks: u64,
+    pub bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub retries: u32,
+    pub errors: u32,
+    pub last_error: Option<String>,
+}
+
+impl TransferStatus {
+    pub fn new(file_name: &str) -> Self {
+        Self {
+            state: TransferState::Running,
+            file_name: file_name.to_string(),
+            blocks: 0,
+            bytes: 0,
+            total_bytes: None,
+            retries: 0,
+            errors: 0,
+            last_error: None,
+        }
+    }
+
+    /// Record a recoverable error such as a bad checksum.
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.errors += 1;
+        self.last_error = Some(message.into());
+    }
+
+    pub fn fail(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        self.last_error = Some(message.clone());
+        self.state = TransferState::Failed(message);
+    }
+}
+
+/// A file transfer protocol running on top of the raw byte stream.
+///
+/// Implementations never touch the port themselves: received bytes are fed
+/// in and every call returns the bytes that must be written back.
+pub trait FileTransfer: Send {
+    /// Begin the transfer. Returns the bytes to send first, if any.
+    fn start(&mut self, now: Instant) -> Vec<u8>;
+
+    /// Handle bytes received from the port.
+    fn on_data(&mut self, data: &[u8], now: Instant) -> Vec<u8>;
+
+    /// Handle timeouts. Called regularly while the transfer is running.
+    fn poll(&mut self, now: Instant) -> Vec<u8>;
+
+    /// Abort the transfer, returning the cancel sequence for the other side.
+    fn cancel(&mut self) -> Vec<u8>;
+
+    fn status(&self) -> &TransferStatus;
+
+    /// Bytes the other side still sends after the transfer finished, such
+    /// as the "OO" ending a ZMODEM session. They are not received data.
+    fn trailer(&self) -> &[u8] {
+        &[]
+    }
+
+    fn is_finished(&self) -> bool {
+        self.status().state != TransferState::Running
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::FileTransfer;
+    use std::path::PathBuf;
+    use std::time::Instant;
+
+    /// A fresh directory for the files written by a test.
+    pub fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("transfer-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// File contents with every byte value, so escaping is exercised.
+    pub fn sample_data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 + i / 251) as u8).collect()
+    }
+
+    /// Pass the output of each side to the other until both finished.
+    pub fn loopback(sender: &mut dyn FileTransfer, receiver: &mut dyn FileTransfer) {
+        let now = Instant::now();
+        let mut to_receiver = sender.start(now);
+        let mut to_sender = receiver.start(now);
+        for _ in 0..10_000 {
+            if sender.is_finished() && receiver.is_finished() {
+                return;
+            }
+            let reply = receiver.on_data(&std::mem::take(&mut to_receiver), now);
+            to_sender.extend(reply);
+            let reply = sender.on_data(&std::mem::take(&mut to_sender), now);
+            to_receiver.extend(reply);
+        }
+        panic!(
+            "transfer did not finish: sender {:?}, receiver {:?}",
+            sender.status(),
+            receiver.status()
+        );
+    }
+}
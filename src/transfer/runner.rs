@@ -0,0 +1,114 @@
+use super::{FileTransfer, TransferStatus};
+use crate::communicationtrait::DataSender;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often timeouts of the running transfer are checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Runs a file transfer, handling its timeouts and retries on a background
+/// thread so they do not depend on how often the GUI repaints.
+pub struct TransferRunner {
+    transfer: Arc<Mutex<Box<dyn FileTransfer>>>,
+    sender: DataSender,
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl TransferRunner {
+    pub fn start(
+        mut transfer: Box<dyn FileTransfer>,
+        sender: DataSender,
+        ctx: egui::Context,
+    ) -> Self {
+        let first = transfer.start(Instant::now());
+        send_reply(&sender, first);
+
+        let transfer = Arc::new(Mutex::new(transfer));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let transfer_clone = Arc::clone(&transfer);
+        let sender_clone = sender.clone();
+        let stop_clone = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            while !stop_clone.load(Ordering::Relaxed) {
+                thread::sleep(POLL_INTERVAL);
+                let Ok(mut transfer) = transfer_clone.lock() else {
+                    break;
+                };
+                if transfer.is_finished() {
+                    break;
+                }
+                let reply = transfer.poll(Instant::now());
+                if !reply.is_empty() {
+                    send_reply(&sender_clone, reply);
+                    ctx.request_repaint();
+                }
+            }
+            ctx.request_repaint();
+        });
+
+        Self {
+            transfer,
+            sender,
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Feed bytes received from the port to the transfer.
+    pub fn on_data(&self, data: &[u8]) {
+        if let Ok(mut transfer) = self.transfer.lock() {
+            let reply = transfer.on_data(data, Instant::now());
+            send_reply(&self.sender, reply);
+        }
+    }
+
+    pub fn cancel(&self) {
+        if let Ok(mut transfer) = self.transfer.lock() {
+            let reply = transfer.cancel();
+            send_reply(&self.sender, reply);
+        }
+    }
+
+    pub fn status(&self) -> Option<TransferStatus> {
+        self.transfer
+            .lock()
+            .ok()
+            .map(|transfer| transfer.status().clone())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.transfer
+            .lock()
+            .map(|transfer| transfer.is_finished())
+            .unwrap_or(true)
+    }
+
+    /// See [`FileTransfer::trailer`].
+    pub fn trailer(&self) -> Vec<u8> {
+        self.transfer
+            .lock()
+            .map(|transfer| transfer.trailer().to_vec())
+            .unwrap_or_default()
+    }
+}
+
+fn send_reply(sender: &DataSender, reply: Vec<u8>) {
+    if !reply.is_empty() && sender.send(reply).is_err() {
+        eprintln!("Port closed, transfer reply not sent");
+    }
+}
+
+impl Drop for TransferRunner {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                eprintln!("Transfer thread panicked");
+            }
+        }
+    }
+}
@@ -0,0 +1,717 @@
+use super::{FileTransfer, TransferState, TransferStatus};
+use crate::checksum::crc16_xmodem;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const SOH: u8 = 0x01;
+const STX: u8 = 0x02;
+const EOT: u8 = 0x04;
+const ACK: u8 = 0x06;
+const NAK: u8 = 0x15;
+const CAN: u8 = 0x18;
+const SUB: u8 = 0x1A;
+const CRC_START: u8 = b'C';
+
+const MAX_RETRIES: u32 = 10;
+/// How long the sender waits for the receiver to ask for the first block.
+const START_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long either side waits for a reply before retrying.
+const BLOCK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Interval between start requests sent by the receiver.
+const START_REQUEST_INTERVAL: Duration = Duration::from_secs(3);
+/// Number of `C` requests before a receiver falls back to checksum mode.
+const CRC_START_ATTEMPTS: u32 = 3;
+
+/// XMODEM family protocol flavours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XmodemVariant {
+    /// 128-byte blocks with an 8-bit checksum.
+    Checksum,
+    /// 128-byte blocks with CRC-16.
+    Crc,
+    /// 1024-byte blocks with CRC-16.
+    OneK,
+    /// Batch transfer with a file name/size header, 1024-byte blocks.
+    Ymodem,
+}
+
+impl std::fmt::Display for XmodemVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XmodemVariant::Checksum => write!(f, "XMODEM"),
+            XmodemVariant::Crc => write!(f, "XMODEM-CRC"),
+            XmodemVariant::OneK => write!(f, "XMODEM-1K"),
+            XmodemVariant::Ymodem => write!(f, "YMODEM"),
+        }
+    }
+}
+
+fn checksum8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+}
+
+/// Build one block: header, block number, its complement, padded data and
+/// the checksum or CRC.
+fn build_block(number: u8, payload: &[u8], size: usize, pad: u8, use_crc: bool) -> Vec<u8> {
+    let mut data = payload.to_vec();
+    data.resize(size, pad);
+
+    let mut block = Vec::with_capacity(size + 5);
+    block.push(if size == 1024 { STX } else { SOH });
+    block.push(number);
+    block.push(!number);
+    block.extend_from_slice(&data);
+    if use_crc {
+        block.extend_from_slice(&crc16_xmodem(&data).to_be_bytes());
+    } else {
+        block.push(checksum8(&data));
+    }
+    block
+}
+
+fn cancel_sequence() -> Vec<u8> {
+    vec![CAN; 5]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SendPhase {
+    /// Waiting for the receiver's initial `C` or NAK.
+    WaitStart,
+    /// YMODEM: block 0 sent, waiting for its ACK.
+    WaitHeaderAck,
+    /// YMODEM: header acknowledged, waiting for the `C` that starts the data.
+    WaitDataStart,
+    WaitBlockAck,
+    WaitEotAck,
+    /// YMODEM: file done, waiting for the `C` before the empty header.
+    WaitBatchEnd,
+    WaitBatchEndAck,
+    Finished,
+}
+
+/// Sends one file with XMODEM or YMODEM.
+pub struct XmodemSender {
+    variant: XmodemVariant,
+    data: Vec<u8>,
+    phase: SendPhase,
+    use_crc: bool,
+    block_number: u8,
+    offset: usize,
+    block_len: usize,
+    last_packet: Vec<u8>,
+    /// Retries of the packet currently in flight.
+    attempts: u32,
+    deadline: Instant,
+    cancel_count: u8,
+    status: TransferStatus,
+}
+
+impl XmodemSender {
+    pub fn new(variant: XmodemVariant, file_name: &str, data: Vec<u8>) -> Self {
+        let mut status = TransferStatus::new(file_name);
+        status.total_bytes = Some(data.len() as u64);
+        Self {
+            variant,
+            data,
+            phase: SendPhase::WaitStart,
+            use_crc: variant != XmodemVariant::Checksum,
+            block_number: 1,
+            offset: 0,
+            block_len: 0,
+            last_packet: Vec::new(),
+            attempts: 0,
+            deadline: Instant::now() + START_TIMEOUT,
+            cancel_count: 0,
+            status,
+        }
+    }
+
+    /// Load `path` and prepare to send it.
+    pub fn from_file(variant: XmodemVariant, path: &str) -> std::io::Result<Self> {
+        let data = std::fs::read(path)?;
+        let name = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string());
+        Ok(Self::new(variant, &name, data))
+    }
+
+    fn send(&mut self, packet: Vec<u8>, now: Instant) -> Vec<u8> {
+        if packet != self.last_packet {
+            self.attempts = 0;
+        }
+        self.last_packet = packet.clone();
+        self.deadline = now + BLOCK_TIMEOUT;
+        packet
+    }
+
+    fn header_block(&self) -> Vec<u8> {
+        let mut header = self.status.file_name.as_bytes().to_vec();
+        header.push(0);
+        header.extend_from_slice(self.data.len().to_string().as_bytes());
+        let size = if header.len() > 128 { 1024 } else { 128 };
+        build_block(0, &header, size, 0, true)
+    }
+
+    fn data_block(&mut self) -> Vec<u8> {
+        let remaining = self.data.len() - self.offset;
+        let size = if self.use_crc
+            && matches!(self.variant, XmodemVariant::OneK | XmodemVariant::Ymodem)
+            && remaining > 128
+        {
+            1024
+        } else {
+            128
+        };
+        self.block_len = remaining.min(size);
+        let payload = &self.data[self.offset..self.offset + self.block_len];
+        build_block(self.block_number, payload, size, SUB, self.use_crc)
+    }
+
+    fn next_data_packet(&mut self, now: Instant) -> Vec<u8> {
+        if self.offset >= self.data.len() {
+            self.phase = SendPhase::WaitEotAck;
+            return self.send(vec![EOT], now);
+        }
+        self.phase = SendPhase::WaitBlockAck;
+        let block = self.data_block();
+        self.send(block, now)
+    }
+
+    fn retry(&mut self, reason: &str, now: Instant) -> Vec<u8> {
+        self.status.retries += 1;
+        self.attempts += 1;
+        self.status.error(reason);
+        if self.attempts > MAX_RETRIES {
+            self.phase = SendPhase::Finished;
+            self.status.fail(format!("Too many retries: {reason}"));
+            return cancel_sequence();
+        }
+        let packet = self.last_packet.clone();
+        self.send(packet, now)
+    }
+
+    fn handle_byte(&mut self, byte: u8, now: Instant) -> Option<Vec<u8>> {
+        if byte == CAN {
+            self.cancel_count += 1;
+            if self.cancel_count >= 2 {
+                self.phase = SendPhase::Finished;
+                self.status.state = TransferState::Cancelled;
+                self.status.last_error = Some("Cancelled by receiver".to_string());
+                return Some(Vec::new());
+            }
+            return None;
+        }
+        self.cancel_count = 0;
+
+        match (self.phase, byte) {
+            (SendPhase::WaitStart, CRC_START | NAK) => {
+                if byte == NAK {
+                    if self.variant == XmodemVariant::Ymodem {
+                        return None;
+                    }
+                    self.use_crc = false;
+                }
+                if self.variant == XmodemVariant::Ymodem {
+                    self.phase = SendPhase::WaitHeaderAck;
+                    let header = self.header_block();
+                    Some(self.send(header, now))
+                } else {
+                    Some(self.next_data_packet(now))
+                }
+            }
+            (SendPhase::WaitHeaderAck, ACK) => {
+                self.phase = SendPhase::WaitDataStart;
+                self.deadline = now + BLOCK_TIMEOUT;
+                // The `C` often arrives in the same chunk as the ACK
+                None
+            }
+            (SendPhase::WaitDataStart, CRC_START) => Some(self.next_data_packet(now)),
+            (SendPhase::WaitBlockAck, ACK) => {
+                self.offset += self.block_len;
+                self.block_number = self.block_number.wrapping_add(1);
+                self.status.blocks += 1;
+                self.status.bytes = self.offset as u64;
+                Some(self.next_data_packet(now))
+            }
+            (SendPhase::WaitEotAck, ACK) => {
+                if self.variant == XmodemVariant::Ymodem {
+                    self.phase = SendPhase::WaitBatchEnd;
+                    self.deadline = now + BLOCK_TIMEOUT;
+                    None
+                } else {
+                    self.phase = SendPhase::Finished;
+                    self.status.state = TransferState::Done;
+                    Some(Vec::new())
+                }
+            }
+            (SendPhase::WaitBatchEnd, CRC_START) => {
+                self.phase = SendPhase::WaitBatchEndAck;
+                let block = build_block(0, &[], 128, 0, true);
+                Some(self.send(block, now))
+            }
+            (SendPhase::WaitBatchEndAck, ACK) => {
+                self.phase = SendPhase::Finished;
+                self.status.state = TransferState::Done;
+                Some(Vec::new())
+            }
+            (SendPhase::WaitEotAck, NAK) => {
+                // YMODEM receivers NAK the first EOT on purpose
+                Some(self.send(vec![EOT], now))
+            }
+            (
+                SendPhase::WaitHeaderAck | SendPhase::WaitBlockAck | SendPhase::WaitBatchEndAck,
+                NAK,
+            ) => Some(self.retry("Block rejected by receiver", now)),
+            _ => None,
+        }
+    }
+}
+
+impl FileTransfer for XmodemSender {
+    fn start(&mut self, now: Instant) -> Vec<u8> {
+        self.deadline = now + START_TIMEOUT;
+        Vec::new()
+    }
+
+    fn on_data(&mut self, data: &[u8], now: Instant) -> Vec<u8> {
+        for &byte in data {
+            if self.phase == SendPhase::Finished {
+                break;
+            }
+            // Anything after a reply that made us send is stale
+            if let Some(reply) = self.handle_byte(byte, now) {
+                return reply;
+            }
+        }
+        Vec::new()
+    }
+
+    fn poll(&mut self, now: Instant) -> Vec<u8> {
+        if self.phase == SendPhase::Finished || now < self.deadline {
+            return Vec::new();
+        }
+        match self.phase {
+            SendPhase::WaitStart => {
+                self.phase = SendPhase::Finished;
+                self.status.fail("Timed out waiting for the receiver");
+                cancel_sequence()
+            }
+            SendPhase::WaitDataStart | SendPhase::WaitBatchEnd => {
+                self.status.retries += 1;
+                self.attempts += 1;
+                self.deadline = now + BLOCK_TIMEOUT;
+                if self.attempts > MAX_RETRIES {
+                    self.phase = SendPhase::Finished;
+                    self.status.fail("Timed out waiting for the receiver");
+                    return cancel_sequence();
+                }
+                Vec::new()
+            }
+            _ => self.retry("Timed out waiting for ACK", now),
+        }
+    }
+
+    fn cancel(&mut self) -> Vec<u8> {
+        self.phase = SendPhase::Finished;
+        self.status.state = TransferState::Cancelled;
+        cancel_sequence()
+    }
+
+    fn status(&self) -> &TransferStatus {
+        &self.status
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReceivePhase {
+    /// Sending `C`/NAK until the first block (or YMODEM header) arrives.
+    Starting,
+    Receiving,
+    /// YMODEM: first EOT was NAKed, waiting for the second one.
+    SecondEot,
+    Finished,
+}
+
+/// Receives files with XMODEM or YMODEM.
+///
+/// For XMODEM `output` is the file to write. For YMODEM it is the directory
+/// where files are stored under the names sent by the other side.
+pub struct XmodemReceiver {
+    variant: XmodemVariant,
+    output: PathBuf,
+    phase: ReceivePhase,
+    use_crc: bool,
+    /// YMODEM: waiting for block 0 rather than data.
+    expect_header: bool,
+    expected_block: u8,
+    buffer: Vec<u8>,
+    data: Vec<u8>,
+    deadline: Instant,
+    start_attempts: u32,
+    /// Consecutive timeouts while waiting for data.
+    timeouts: u32,
+    files_received: u32,
+    status: TransferStatus,
+}
+
+impl XmodemReceiver {
+    pub fn new(variant: XmodemVariant, output: &str) -> Self {
+        let name = Path::new(output)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self {
+            variant,
+            output: PathBuf::from(output),
+            phase: ReceivePhase::Starting,
+            use_crc: variant != XmodemVariant::Checksum,
+            expect_header: variant == XmodemVariant::Ymodem,
+            expected_block: 1,
+            buffer: Vec::new(),
+            data: Vec::new(),
+            deadline: Instant::now(),
+            start_attempts: 0,
+            timeouts: 0,
+            files_received: 0,
+            status: TransferStatus::new(&name),
+        }
+    }
+
+    fn start_request(&self) -> u8 {
+        if self.use_crc {
+            CRC_START
+        } else {
+            NAK
+        }
+    }
+
+    fn block_size(header: u8) -> usize {
+        if header == STX {
+            1024
+        } else {
+            128
+        }
+    }
+
+    fn finish_file(&mut self) -> Result<(), String> {
+        let mut data = std::mem::take(&mut self.data);
+        let path = if self.variant == XmodemVariant::Ymodem {
+            if let Some(size) = self.status.total_bytes {
+                data.truncate(size as usize);
+            }
+            self.output.join(&self.status.file_name)
+        } else {
+            // Plain XMODEM has no length field, drop the padding
+            while data.last() == Some(&SUB) {
+                data.pop();
+            }
+            self.output.clone()
+        };
+        std::fs::write(&path, &data)
+            .map_err(|e| format!("Cannot write \"{}\": {e}", path.display()))?;
+        self.files_received += 1;
+        Ok(())
+    }
+
+    /// Parse a YMODEM block 0. Returns false for the empty block that ends
+    /// the batch.
+    fn parse_header(&mut self, payload: &[u8]) -> bool {
+        let mut fields = payload.split(|&b| b == 0);
+        let name = fields.next().unwrap_or_default();
+        if name.is_empty() {
+            return false;
+        }
+        // Never write outside the chosen directory
+        let name = String::from_utf8_lossy(name);
+        let name = Path::new(name.as_ref())
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "received.bin".to_string());
+
+        let size = fields
+            .next()
+            .and_then(|info| {
+                String::from_utf8_lossy(info)
+                    .split(' ')
+                    .next()
+                    .map(String::from)
+            })
+            .and_then(|size| size.parse::<u64>().ok());
+
+        self.status.file_name = name;
+        self.status.total_bytes = size;
+        self.status.bytes = 0;
+        self.status.blocks = 0;
+        true
+    }
+
+    fn fail(&mut self, message: &str) -> Vec<u8> {
+        self.phase = ReceivePhase::Finished;
+        self.status.fail(message);
+        cancel_sequence()
+    }
+
+    /// Try to consume one block or control byte from the buffer. Returns
+    /// `None` when more data is needed.
+    fn process_buffer(&mut self, now: Instant) -> Option<Vec<u8>> {
+        let &first = self.buffer.first()?;
+        match first {
+            SOH | STX => {
+                let size = Self::block_size(first);
+                let check_len = if self.use_crc { 2 } else { 1 };
+                let total = 3 + size + check_len;
+                if self.buffer.len() < total {
+                    return None;
+                }
+                let block: Vec<u8> = self.buffer.drain(..total).collect();
+                Some(self.handle_block(&block, size, now))
+            }
+            EOT => {
+                self.buffer.remove(0);
+                Some(self.handle_eot(now))
+            }
+            CAN => {
+                if self.buffer.len() < 2 {
+                    return None;
+                }
+                if self.buffer[1] == CAN {
+                    self.buffer.clear();
+                    self.phase = ReceivePhase::Finished;
+                    self.status.state = TransferState::Cancelled;
+                    self.status.last_error = Some("Cancelled by sender".to_string());
+                    return Some(Vec::new());
+                }
+                self.buffer.remove(0);
+                Some(Vec::new())
+            }
+            _ => {
+                // Line noise between blocks
+                self.buffer.remove(0);
+                Some(Vec::new())
+            }
+        }
+    }
+
+    fn handle_block(&mut self, block: &[u8], size: usize, now: Instant) -> Vec<u8> {
+        let number = block[1];
+        let payload = &block[3..3 + size];
+        let valid_check = if self.use_crc {
+            let received = u16::from_be_bytes([block[3 + size], block[4 + size]]);
+            received == crc16_xmodem(payload)
+        } else {
+            block[3 + size] == checksum8(payload)
+        };
+
+        if number != !block[2] || !valid_check {
+            self.status.error(format!("Corrupt block {number}"));
+            self.buffer.clear();
+            return vec![NAK];
+        }
+
+        self.deadline = now + BLOCK_TIMEOUT;
+        self.timeouts = 0;
+
+        if self.expect_header && number == 0 {
+            if self.parse_header(payload) {
+                self.expect_header = false;
+                self.expected_block = 1;
+                self.phase = ReceivePhase::Receiving;
+                return vec![ACK, CRC_START];
+            }
+            self.phase = ReceivePhase::Finished;
+            self.status.state = TransferState::Done;
+            return vec![ACK];
+        }
+        if self.expect_header {
+            // Lost the header ACK, the sender resent the last data block
+            return vec![ACK];
+        }
+
+        self.phase = ReceivePhase::Receiving;
+        if number == self.expected_block {
+            self.data.extend_from_slice(payload);
+            self.expected_block = self.expected_block.wrapping_add(1);
+            self.status.blocks += 1;
+            self.status.bytes = match self.status.total_bytes {
+                Some(total) => (self.data.len() as u64).min(total),
+                None => self.data.len() as u64,
+            };
+            vec![ACK]
+        } else if number == self.expected_block.wrapping_sub(1) {
+            // Our ACK was lost and the sender repeated the block
+            vec![ACK]
+        } else {
+            self.fail(&format!(
+                "Block sequence error: expected {}, got {number}",
+                self.expected_block
+            ))
+        }
+    }
+
+    fn handle_eot(&mut self, now: Instant) -> Vec<u8> {
+        self.deadline = now + BLOCK_TIMEOUT;
+        match (self.variant, self.phase) {
+            (XmodemVariant::Ymodem, ReceivePhase::Receiving) => {
+                self.phase = ReceivePhase::SecondEot;
+                vec![NAK]
+            }
+            (XmodemVariant::Ymodem, ReceivePhase::SecondEot) => {
+                if let Err(e) = self.finish_file() {
+                    return self.fail(&e);
+                }
+                self.expect_header = true;
+                self.start_attempts = 0;
+                self.phase = ReceivePhase::Starting;
+                vec![ACK, CRC_START]
+            }
+            (XmodemVariant::Ymodem, _) => vec![ACK],
+            (_, _) => {
+                if let Err(e) = self.finish_file() {
+                    return self.fail(&e);
+                }
+                self.phase = ReceivePhase::Finished;
+                self.status.state = TransferState::Done;
+                vec![ACK]
+            }
+        }
+    }
+}
+
+impl FileTransfer for XmodemReceiver {
+    fn start(&mut self, now: Instant) -> Vec<u8> {
+        self.deadline = now + START_REQUEST_INTERVAL;
+        self.start_attempts = 1;
+        vec![self.start_request()]
+    }
+
+    fn on_data(&mut self, data: &[u8], now: Instant) -> Vec<u8> {
+        self.buffer.extend_from_slice(data);
+        let mut reply = Vec::new();
+        while self.phase != ReceivePhase::Finished {
+            match self.process_buffer(now) {
+                Some(bytes) => reply.extend(bytes),
+                None => break,
+            }
+        }
+        reply
+    }
+
+    fn poll(&mut self, now: Instant) -> Vec<u8> {
+        if self.phase == ReceivePhase::Finished || now < self.deadline {
+            return Vec::new();
+        }
+
+        if self.phase == ReceivePhase::Starting {
+            self.start_attempts += 1;
+            if self.start_attempts > MAX_RETRIES {
+                return self.fail("No response from sender");
+            }
+            if self.variant != XmodemVariant::Ymodem
+                && self.files_received == 0
+                && self.use_crc
+                && self.start_attempts > CRC_START_ATTEMPTS
+            {
+                self.use_crc = false;
+            }
+            self.deadline = now + START_REQUEST_INTERVAL;
+            return vec![self.start_request()];
+        }
+
+        self.status.retries += 1;
+        self.timeouts += 1;
+        if self.timeouts > MAX_RETRIES {
+            return self.fail("Timed out waiting for data");
+        }
+        self.status.error("Timed out waiting for data");
+        self.buffer.clear();
+        self.deadline = now + BLOCK_TIMEOUT;
+        vec![NAK]
+    }
+
+    fn cancel(&mut self) -> Vec<u8> {
+        self.phase = ReceivePhase::Finished;
+        self.status.state = TransferState::Cancelled;
+        cancel_sequence()
+    }
+
+    fn status(&self) -> &TransferStatus {
+        &self.status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfer::tests::{loopback, sample_data, temp_dir};
+
+    fn send_file(variant: XmodemVariant, data: Vec<u8>, name: &str) -> Vec<u8> {
+        let dir = temp_dir(name);
+        let output = if variant == XmodemVariant::Ymodem {
+            dir.clone()
+        } else {
+            dir.join("out.bin")
+        };
+        let mut sender = XmodemSender::new(variant, "file.bin", data);
+        let mut receiver = XmodemReceiver::new(variant, output.to_str().unwrap());
+        loopback(&mut sender, &mut receiver);
+
+        assert_eq!(sender.status().state, TransferState::Done);
+        assert_eq!(receiver.status().state, TransferState::Done);
+        let path = if variant == XmodemVariant::Ymodem {
+            dir.join("file.bin")
+        } else {
+            output
+        };
+        std::fs::read(path).unwrap()
+    }
+
+    #[test]
+    fn loopback_all_variants() {
+        // Plain XMODEM strips SUB padding, so the data must not end with it
+        let mut data = sample_data(3000);
+        data.push(b'!');
+        for (variant, name) in [
+            (XmodemVariant::Checksum, "checksum"),
+            (XmodemVariant::Crc, "crc"),
+            (XmodemVariant::OneK, "onek"),
+            (XmodemVariant::Ymodem, "ymodem"),
+        ] {
+            assert_eq!(send_file(variant, data.clone(), name), data, "{variant}");
+        }
+    }
+
+    #[test]
+    fn loopback_empty_file() {
+        for (variant, name) in [
+            (XmodemVariant::Crc, "empty-crc"),
+            (XmodemVariant::Ymodem, "empty-ymodem"),
+        ] {
+            assert!(send_file(variant, Vec::new(), name).is_empty(), "{variant}");
+        }
+    }
+
+    #[test]
+    fn corrupt_block_is_resent() {
+        let dir = temp_dir("corrupt");
+        let output = dir.join("out.bin");
+        let data = b"hello".to_vec();
+        let mut sender = XmodemSender::new(XmodemVariant::Crc, "file.bin", data.clone());
+        let mut receiver = XmodemReceiver::new(XmodemVariant::Crc, output.to_str().unwrap());
+        let now = Instant::now();
+
+        receiver.start(now);
+        let mut block = sender.on_data(&[CRC_START], now);
+        block[10] ^= 0xFF;
+        assert_eq!(receiver.on_data(&block, now), vec![NAK]);
+        let block = sender.on_data(&[NAK], now);
+        assert_eq!(receiver.on_data(&block, now), vec![ACK]);
+        let eot = sender.on_data(&[ACK], now);
+        assert_eq!(receiver.on_data(&eot, now), vec![ACK]);
+        sender.on_data(&[ACK], now);
+
+        assert_eq!(sender.status().state, TransferState::Done);
+        assert_eq!(receiver.status().errors, 1);
+        assert_eq!(std::fs::read(output).unwrap(), data);
+    }
+}
@@ -0,0 +1,968 @@
+use super::{FileTransfer, TransferState, TransferStatus};
+use crate::checksum::{crc16_xmodem, crc32};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const ZPAD: u8 = b'*';
+const ZDLE: u8 = 0x18;
+const ZBIN: u8 = b'A';
+const ZHEX: u8 = b'B';
+const ZBIN32: u8 = b'C';
+const XON: u8 = 0x11;
+
+// Frame types
+const ZRQINIT: u8 = 0;
+const ZRINIT: u8 = 1;
+const ZSINIT: u8 = 2;
+const ZACK: u8 = 3;
+const ZFILE: u8 = 4;
+const ZSKIP: u8 = 5;
+const ZNAK: u8 = 6;
+const ZABORT: u8 = 7;
+const ZFIN: u8 = 8;
+const ZRPOS: u8 = 9;
+const ZDATA: u8 = 10;
+const ZEOF: u8 = 11;
+const ZFERR: u8 = 12;
+const ZCAN: u8 = 16;
+const ZCOMMAND: u8 = 18;
+
+// Data subpacket terminators
+const ZCRCE: u8 = b'h';
+const ZCRCG: u8 = b'i';
+const ZCRCQ: u8 = b'j';
+const ZCRCW: u8 = b'k';
+const ZRUB0: u8 = b'l';
+const ZRUB1: u8 = b'm';
+
+// ZRINIT capability flags
+const CANFDX: u8 = 0x01;
+const CANOVIO: u8 = 0x02;
+const CANFC32: u8 = 0x20;
+
+/// Start of a hex ZRQINIT header, sent by `sz` when a transfer begins.
+pub const ZRQINIT_SEQUENCE: &[u8] = b"**\x18B00";
+
+const SUBPACKET_SIZE: usize = 1024;
+/// Bytes sent before waiting for the receiver to acknowledge.
+const WINDOW_SIZE: usize = 8 * SUBPACKET_SIZE;
+/// Longest data subpacket accepted before treating the stream as garbage.
+const MAX_SUBPACKET: usize = 8 * 1024;
+const MAX_RETRIES: u32 = 10;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Abort sequence: eight CANs followed by backspaces to clean up a shell line.
+pub fn cancel_sequence() -> Vec<u8> {
+    let mut seq = vec![ZDLE; 8];
+    seq.extend_from_slice(&[0x08; 8]);
+    seq
+}
+
+fn escape_into(out: &mut Vec<u8>, data: &[u8]) {
+    for &byte in data {
+        match byte {
+            ZDLE | 0x10 | 0x11 | 0x13 | 0x90 | 0x91 | 0x93 => {
+                out.push(ZDLE);
+                out.push(byte ^ 0x40);
+            }
+            _ => out.push(byte),
+        }
+    }
+}
+
+fn hex_header(frame_type: u8, data: [u8; 4]) -> Vec<u8> {
+    let bytes = [frame_type, data[0], data[1], data[2], data[3]];
+    let crc = crc16_xmodem(&bytes);
+
+    let mut out = vec![ZPAD, ZPAD, ZDLE, ZHEX];
+    for byte in bytes.iter().chain(crc.to_be_bytes().iter()) {
+        out.extend_from_slice(format!("{byte:02x}").as_bytes());
+    }
+    out.extend_from_slice(b"\r\x8a");
+    if frame_type != ZFIN && frame_type != ZACK {
+        out.push(XON);
+    }
+    out
+}
+
+fn bin_header(frame_type: u8, data: [u8; 4], use_crc32: bool) -> Vec<u8> {
+    let bytes = [frame_type, data[0], data[1], data[2], data[3]];
+    let mut out = vec![ZPAD, ZDLE, if use_crc32 { ZBIN32 } else { ZBIN }];
+    escape_into(&mut out, &bytes);
+    if use_crc32 {
+        escape_into(&mut out, &crc32(&bytes).to_le_bytes());
+    } else {
+        escape_into(&mut out, &crc16_xmodem(&bytes).to_be_bytes());
+    }
+    out
+}
+
+fn data_subpacket(data: &[u8], end: u8, use_crc32: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / 8 + 8);
+    escape_into(&mut out, data);
+    out.push(ZDLE);
+    out.push(end);
+
+    let mut covered = data.to_vec();
+    covered.push(end);
+    if use_crc32 {
+        escape_into(&mut out, &crc32(&covered).to_le_bytes());
+    } else {
+        escape_into(&mut out, &crc16_xmodem(&covered).to_be_bytes());
+    }
+    if end == ZCRCW {
+        out.push(XON);
+    }
+    out
+}
+
+fn position(pos: u64) -> [u8; 4] {
+    (pos as u32).to_le_bytes()
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    frame_type: u8,
+    data: [u8; 4],
+}
+
+impl Header {
+    fn position(&self) -> u64 {
+        u32::from_le_bytes(self.data) as u64
+    }
+
+    /// ZF0, the first flag byte, shares storage with ZP3.
+    fn zf0(&self) -> u8 {
+        self.data[3]
+    }
+}
+
+enum Event {
+    Header(Header),
+    Data { payload: Vec<u8>, end: u8 },
+    BadHeader,
+    BadData,
+    Cancel,
+}
+
+enum ParseState {
+    /// Looking for ZPAD.
+    Hunt,
+    /// Got ZPAD, waiting for ZDLE.
+    Pad,
+    /// Got ZPAD ZDLE, waiting for the header format byte.
+    Format,
+    Hex(Vec<u8>),
+    Binary {
+        use_crc32: bool,
+        bytes: Vec<u8>,
+        escaped: bool,
+    },
+    Data {
+        use_crc32: bool,
+        bytes: Vec<u8>,
+        escaped: bool,
+        end: Option<u8>,
+        crc: Vec<u8>,
+    },
+}
+
+/// Splits the incoming byte stream into ZMODEM headers and data subpackets.
+struct Parser {
+    state: ParseState,
+    cancel_count: usize,
+}
+
+impl Parser {
+    fn new() -> Self {
+        Self {
+            state: ParseState::Hunt,
+            cancel_count: 0,
+        }
+    }
+
+    fn feed(&mut self, input: &[u8]) -> Vec<Event> {
+        let mut events = Vec::new();
+        for &byte in input {
+            if byte == ZDLE {
+                self.cancel_count += 1;
+                if self.cancel_count >= 5 {
+                    self.cancel_count = 0;
+                    self.state = ParseState::Hunt;
+                    events.push(Event::Cancel);
+                    continue;
+                }
+            } else {
+                self.cancel_count = 0;
+            }
+            if let Some(event) = self.feed_byte(byte) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Undo ZDLE escaping. Returns `None` for sequences that are not data.
+    fn unescape(byte: u8) -> Option<u8> {
+        match byte {
+            ZRUB0 => Some(0x7F),
+            ZRUB1 => Some(0xFF),
+            _ if byte & 0x60 == 0x40 => Some(byte ^ 0x40),
+            _ => None,
+        }
+    }
+
+    fn is_flow_control(byte: u8) -> bool {
+        matches!(byte, 0x11 | 0x13 | 0x91 | 0x93)
+    }
+
+    fn header_done(&mut self, header: Header, use_crc32: bool) -> Event {
+        self.state = if matches!(header.frame_type, ZFILE | ZDATA | ZSINIT | ZCOMMAND) {
+            ParseState::Data {
+                use_crc32,
+                bytes: Vec::new(),
+                escaped: false,
+                end: None,
+                crc: Vec::new(),
+            }
+        } else {
+            ParseState::Hunt
+        };
+        Event::Header(header)
+    }
+
+    fn feed_byte(&mut self, byte: u8) -> Option<Event> {
+        match &mut self.state {
+            ParseState::Hunt => {
+                if byte == ZPAD {
+                    self.state = ParseState::Pad;
+                }
+                None
+            }
+            ParseState::Pad => {
+                match byte {
+                    ZPAD => {}
+                    ZDLE => self.state = ParseState::Format,
+                    _ => self.state = ParseState::Hunt,
+                }
+                None
+            }
+            ParseState::Format => {
+                self.state = match byte {
+                    ZHEX => ParseState::Hex(Vec::new()),
+                    ZBIN | ZBIN32 => ParseState::Binary {
+                        use_crc32: byte == ZBIN32,
+                        bytes: Vec::new(),
+                        escaped: false,
+                    },
+                    _ => ParseState::Hunt,
+                };
+                None
+            }
+            ParseState::Hex(digits) => {
+                if !byte.is_ascii_hexdigit() {
+                    self.state = ParseState::Hunt;
+                    return Some(Event::BadHeader);
+                }
+                digits.push(byte);
+                if digits.len() < 14 {
+                    return None;
+                }
+                let text = String::from_utf8_lossy(digits).into_owned();
+                let bytes: Vec<u8> = (0..7)
+                    .filter_map(|i| u8::from_str_radix(&text[i * 2..i * 2 + 2], 16).ok())
+                    .collect();
+                let crc = u16::from_be_bytes([bytes[5], bytes[6]]);
+                if crc != crc16_xmodem(&bytes[..5]) {
+                    self.state = ParseState::Hunt;
+                    return Some(Event::BadHeader);
+                }
+                let header = Header {
+                    frame_type: bytes[0],
+                    data: [bytes[1], bytes[2], bytes[3], bytes[4]],
+                };
+                // Hex headers are never followed by data subpackets
+                self.state = ParseState::Hunt;
+                Some(Event::Header(header))
+            }
+            ParseState::Binary {
+                use_crc32,
+                bytes,
+                escaped,
+            } => {
+                let value = if *escaped {
+                    *escaped = false;
+                    match Self::unescape(byte) {
+                        Some(value) => value,
+                        None => {
+                            self.state = ParseState::Hunt;
+                            return Some(Event::BadHeader);
+                        }
+                    }
+                } else if byte == ZDLE {
+                    *escaped = true;
+                    return None;
+                } else if Self::is_flow_control(byte) {
+                    return None;
+                } else {
+                    byte
+                };
+
+                bytes.push(value);
+                let crc_len = if *use_crc32 { 4 } else { 2 };
+                if bytes.len() < 5 + crc_len {
+                    return None;
+                }
+                let valid = if *use_crc32 {
+                    let crc = u32::from_le_bytes([bytes[5], bytes[6], bytes[7], bytes[8]]);
+                    crc == crc32(&bytes[..5])
+                } else {
+                    u16::from_be_bytes([bytes[5], bytes[6]]) == crc16_xmodem(&bytes[..5])
+                };
+                if !valid {
+                    self.state = ParseState::Hunt;
+                    return Some(Event::BadHeader);
+                }
+                let header = Header {
+                    frame_type: bytes[0],
+                    data: [bytes[1], bytes[2], bytes[3], bytes[4]],
+                };
+                let use_crc32 = *use_crc32;
+                Some(self.header_done(header, use_crc32))
+            }
+            ParseState::Data {
+                use_crc32,
+                bytes,
+                escaped,
+                end,
+                crc,
+            } => {
+                let value = if *escaped {
+                    *escaped = false;
+                    if end.is_none() && (ZCRCE..=ZCRCW).contains(&byte) {
+                        *end = Some(byte);
+                        return None;
+                    }
+                    match Self::unescape(byte) {
+                        Some(value) => value,
+                        None => {
+                            self.state = ParseState::Hunt;
+                            return Some(Event::BadData);
+                        }
+                    }
+                } else if byte == ZDLE {
+                    *escaped = true;
+                    return None;
+                } else if Self::is_flow_control(byte) {
+                    return None;
+                } else {
+                    byte
+                };
+
+                let Some(end_byte) = *end else {
+                    bytes.push(value);
+                    if bytes.len() > MAX_SUBPACKET {
+                        self.state = ParseState::Hunt;
+                        return Some(Event::BadData);
+                    }
+                    return None;
+                };
+
+                crc.push(value);
+                let crc_len = if *use_crc32 { 4 } else { 2 };
+                if crc.len() < crc_len {
+                    return None;
+                }
+
+                let mut covered = bytes.clone();
+                covered.push(end_byte);
+                let valid = if *use_crc32 {
+                    u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]]) == crc32(&covered)
+                } else {
+                    u16::from_be_bytes([crc[0], crc[1]]) == crc16_xmodem(&covered)
+                };
+                if !valid {
+                    self.state = ParseState::Hunt;
+                    return Some(Event::BadData);
+                }
+
+                let payload = std::mem::take(bytes);
+                if matches!(end_byte, ZCRCE | ZCRCW) {
+                    self.state = ParseState::Hunt;
+                } else {
+                    *end = None;
+                    crc.clear();
+                }
+                Some(Event::Data {
+                    payload,
+                    end: end_byte,
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SendPhase {
+    WaitReceiverInit,
+    /// ZFILE sent, waiting for ZRPOS or ZSKIP.
+    WaitFilePosition,
+    /// A window ending in ZCRCW was sent, waiting for ZACK.
+    WaitWindowAck,
+    /// ZEOF sent, waiting for the receiver's ZRINIT.
+    WaitEofAck,
+    WaitFin,
+    Finished,
+}
+
+/// Sends one file with ZMODEM, e.g. to a target running `rz`.
+pub struct ZmodemSender {
+    data: Vec<u8>,
+    start_remote: bool,
+    parser: Parser,
+    phase: SendPhase,
+    use_crc32: bool,
+    position: usize,
+    window_end: usize,
+    last_packet: Vec<u8>,
+    deadline: Instant,
+    attempts: u32,
+    status: TransferStatus,
+}
+
+impl ZmodemSender {
+    /// With `start_remote` set, `rz\r` is typed first to launch the receiver
+    /// on a shell prompt.
+    pub fn from_file(path: &str, start_remote: bool) -> std::io::Result<Self> {
+        let data = std::fs::read(path)?;
+        let name = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string());
+
+        let mut status = TransferStatus::new(&name);
+        status.total_bytes = Some(data.len() as u64);
+        Ok(Self {
+            data,
+            start_remote,
+            parser: Parser::new(),
+            phase: SendPhase::WaitReceiverInit,
+            use_crc32: false,
+            position: 0,
+            window_end: 0,
+            last_packet: Vec::new(),
+            deadline: Instant::now() + TIMEOUT,
+            attempts: 0,
+            status,
+        })
+    }
+
+    fn send(&mut self, packet: Vec<u8>, now: Instant) -> Vec<u8> {
+        if packet != self.last_packet {
+            self.attempts = 0;
+        }
+        self.last_packet = packet.clone();
+        self.deadline = now + TIMEOUT;
+        packet
+    }
+
+    fn file_header(&self) -> Vec<u8> {
+        let mut info = self.status.file_name.as_bytes().to_vec();
+        info.push(0);
+        info.extend_from_slice(
+            format!("{} 0 100644 0 1 {}", self.data.len(), self.data.len()).as_bytes(),
+        );
+        info.push(0);
+
+        let mut packet = bin_header(ZFILE, [0; 4], self.use_crc32);
+        packet.extend(data_subpacket(&info, ZCRCW, self.use_crc32));
+        packet
+    }
+
+    /// Send the next window of data starting at `self.position`.
+    fn window(&mut self, now: Instant) -> Vec<u8> {
+        let end = (self.position + WINDOW_SIZE).min(self.data.len());
+        let mut packet = Vec::new();
+
+        // An empty file, or a resume at its end, goes straight to ZEOF: a
+        // ZDATA without subpackets would make the receiver read ZEOF as data
+        if self.position < end {
+            packet = bin_header(ZDATA, position(self.position as u64), self.use_crc32);
+            let chunks: Vec<&[u8]> = self.data[self.position..end]
+                .chunks(SUBPACKET_SIZE)
+                .collect();
+            let last = chunks.len() - 1;
+            for (index, chunk) in chunks.into_iter().enumerate() {
+                let terminator = match (index == last, end == self.data.len()) {
+                    (false, _) => ZCRCG,
+                    (true, true) => ZCRCE,
+                    (true, false) => ZCRCW,
+                };
+                packet.extend(data_subpacket(chunk, terminator, self.use_crc32));
+            }
+        }
+
+        self.window_end = end;
+        if end == self.data.len() {
+            packet.extend(bin_header(ZEOF, position(end as u64), self.use_crc32));
+            self.phase = SendPhase::WaitEofAck;
+        } else {
+            self.phase = SendPhase::WaitWindowAck;
+        }
+        self.send(packet, now)
+    }
+
+    fn finish_session(&mut self, now: Instant) -> Vec<u8> {
+        self.phase = SendPhase::WaitFin;
+        self.send(hex_header(ZFIN, [0; 4]), now)
+    }
+
+    fn handle_header(&mut self, header: Header, now: Instant) -> Vec<u8> {
+        match (header.frame_type, self.phase) {
+            (ZRINIT, SendPhase::WaitReceiverInit) => {
+                self.use_crc32 = header.zf0() & CANFC32 != 0;
+                self.phase = SendPhase::WaitFilePosition;
+                let packet = self.file_header();
+                self.send(packet, now)
+            }
+            (ZRINIT, SendPhase::WaitEofAck) => {
+                self.status.bytes = self.data.len() as u64;
+                self.finish_session(now)
+            }
+            (
+                ZRPOS,
+                SendPhase::WaitFilePosition | SendPhase::WaitWindowAck | SendPhase::WaitEofAck,
+            ) => {
+                let requested = (header.position() as usize).min(self.data.len());
+                if self.phase == SendPhase::WaitFilePosition {
+                    if requested > 0 {
+                        self.status.last_error = Some(format!("Resuming at byte {requested}"));
+                    }
+                } else {
+                    self.status.retries += 1;
+                    self.status
+                        .error(format!("Receiver asked to resend from {requested}"));
+                }
+                self.position = requested;
+                self.status.bytes = requested as u64;
+                self.window(now)
+            }
+            (ZACK, SendPhase::WaitWindowAck) if header.position() as usize == self.window_end => {
+                self.position = self.window_end;
+                self.status.bytes = self.position as u64;
+                self.status.blocks += 1;
+                self.window(now)
+            }
+            (ZSKIP, SendPhase::WaitFilePosition) => {
+                self.status.last_error = Some("Receiver skipped the file".to_string());
+                self.finish_session(now)
+            }
+            (ZFIN, SendPhase::WaitFin) => {
+                self.phase = SendPhase::Finished;
+                self.status.state = TransferState::Done;
+                b"OO".to_vec()
+            }
+            (ZNAK, _) => {
+                self.status.retries += 1;
+                self.last_packet.clone()
+            }
+            (ZABORT | ZFERR | ZCAN, _) => {
+                self.phase = SendPhase::Finished;
+                self.status.fail("Aborted by receiver");
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl FileTransfer for ZmodemSender {
+    fn start(&mut self, now: Instant) -> Vec<u8> {
+        let mut packet = Vec::new();
+        if self.start_remote {
+            packet.extend_from_slice(b"rz\r");
+        }
+        packet.extend(hex_header(ZRQINIT, [0; 4]));
+        self.send(packet, now)
+    }
+
+    fn on_data(&mut self, data: &[u8], now: Instant) -> Vec<u8> {
+        let mut reply = Vec::new();
+        for event in self.parser.feed(data) {
+            if self.phase == SendPhase::Finished {
+                break;
+            }
+            match event {
+                Event::Header(header) => reply.extend(self.handle_header(header, now)),
+                Event::Cancel => {
+                    self.phase = SendPhase::Finished;
+                    self.status.state = TransferState::Cancelled;
+                    self.status.last_error = Some("Cancelled by receiver".to_string());
+                }
+                Event::BadHeader | Event::BadData => self.status.error("Corrupt frame"),
+                Event::Data { .. } => {}
+            }
+        }
+        reply
+    }
+
+    fn poll(&mut self, now: Instant) -> Vec<u8> {
+        if self.phase == SendPhase::Finished || now < self.deadline {
+            return Vec::new();
+        }
+        self.attempts += 1;
+        self.status.retries += 1;
+        if self.attempts > MAX_RETRIES {
+            self.phase = SendPhase::Finished;
+            self.status.fail("Timed out waiting for the receiver");
+            return cancel_sequence();
+        }
+        self.deadline = now + TIMEOUT;
+        if self.phase == SendPhase::WaitWindowAck {
+            // Resume from the last acknowledged position
+            return self.window(now);
+        }
+        self.last_packet.clone()
+    }
+
+    fn cancel(&mut self) -> Vec<u8> {
+        self.phase = SendPhase::Finished;
+        self.status.state = TransferState::Cancelled;
+        cancel_sequence()
+    }
+
+    fn status(&self) -> &TransferStatus {
+        &self.status
+    }
+}
+
+/// Which header the next data subpacket belongs to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expecting {
+    Nothing,
+    FileInfo,
+    FileData,
+    SessionInit,
+}
+
+/// Receives files sent with `sz`, writing them to a directory.
+///
+/// Data is written as it arrives, so an interrupted transfer leaves a
+/// partial file that the next transfer of the same file resumes.
+pub struct ZmodemReceiver {
+    directory: PathBuf,
+    parser: Parser,
+    expecting: Expecting,
+    file: Option<File>,
+    position: u64,
+    finished: bool,
+    last_packet: Vec<u8>,
+    deadline: Instant,
+    attempts: u32,
+    status: TransferStatus,
+}
+
+impl ZmodemReceiver {
+    pub fn new(directory: &str) -> Self {
+        Self {
+            directory: PathBuf::from(directory),
+            parser: Parser::new(),
+            expecting: Expecting::Nothing,
+            file: None,
+            position: 0,
+            finished: false,
+            last_packet: Vec::new(),
+            deadline: Instant::now() + TIMEOUT,
+            attempts: 0,
+            status: TransferStatus::new(""),
+        }
+    }
+
+    fn send(&mut self, packet: Vec<u8>, now: Instant) -> Vec<u8> {
+        if packet != self.last_packet {
+            self.attempts = 0;
+        }
+        self.last_packet = packet.clone();
+        self.deadline = now + TIMEOUT;
+        packet
+    }
+
+    fn receiver_init() -> Vec<u8> {
+        hex_header(ZRINIT, [0, 0, 0, CANFDX | CANOVIO | CANFC32])
+    }
+
+    fn fail(&mut self, message: String) -> Vec<u8> {
+        self.finished = true;
+        self.file = None;
+        self.status.fail(message);
+        cancel_sequence()
+    }
+
+    /// Handle the ZFILE info block: `name\0size mtime mode ...\0`.
+    fn open_file(&mut self, info: &[u8], now: Instant) -> Vec<u8> {
+        let mut fields = info.split(|&b| b == 0);
+        let name = String::from_utf8_lossy(fields.next().unwrap_or_default()).into_owned();
+        // Never write outside the chosen directory
+        let name = Path::new(&name)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "received.bin".to_string());
+        let size = fields
+            .next()
+            .and_then(|rest| {
+                String::from_utf8_lossy(rest)
+                    .split(' ')
+                    .next()
+                    .map(String::from)
+            })
+            .and_then(|size| size.parse::<u64>().ok());
+
+        self.status.file_name = name.clone();
+        self.status.total_bytes = size;
+        self.status.blocks = 0;
+
+        let path = self.directory.join(&name);
+        let existing = std::fs::metadata(&path).map(|meta| meta.len()).ok();
+
+        let opened = match (existing, size) {
+            (Some(existing), Some(size)) if existing == size => {
+                self.status.bytes = size;
+                self.status.last_error = Some(format!("{name} is already complete, skipped"));
+                return self.send(hex_header(ZSKIP, [0; 4]), now);
+            }
+            (Some(existing), Some(size)) if existing < size => {
+                self.position = existing;
+                self.status.last_error = Some(format!("Resuming {name} at byte {existing}"));
+                OpenOptions::new().append(true).open(&path)
+            }
+            _ => {
+                self.position = 0;
+                File::create(&path)
+            }
+        };
+
+        match opened {
+            Ok(file) => {
+                self.file = Some(file);
+                self.status.bytes = self.position;
+                self.send(hex_header(ZRPOS, position(self.position)), now)
+            }
+            Err(e) => self.fail(format!("Cannot write \"{}\": {e}", path.display())),
+        }
+    }
+
+    fn handle_header(&mut self, header: Header, now: Instant) -> Vec<u8> {
+        self.expecting = Expecting::Nothing;
+        match header.frame_type {
+            ZRQINIT => self.send(Self::receiver_init(), now),
+            ZSINIT => {
+                self.expecting = Expecting::SessionInit;
+                Vec::new()
+            }
+            ZFILE => {
+                self.expecting = Expecting::FileInfo;
+                Vec::new()
+            }
+            ZDATA => {
+                if self.file.is_some() && header.position() == self.position {
+                    self.expecting = Expecting::FileData;
+                    self.deadline = now + TIMEOUT;
+                    Vec::new()
+                } else {
+                    self.send(hex_header(ZRPOS, position(self.position)), now)
+                }
+            }
+            ZEOF => {
+                if header.position() != self.position {
+                    // Data still in flight, the sender will repeat ZEOF
+                    return Vec::new();
+                }
+                if let Some(mut file) = self.file.take() {
+                    if let Err(e) = file.flush() {
+                        return self.fail(format!("Cannot write file: {e}"));
+                    }
+                }
+                self.send(Self::receiver_init(), now)
+            }
+            ZFIN => {
+                self.finished = true;
+                self.status.state = TransferState::Done;
+                hex_header(ZFIN, [0; 4])
+            }
+            ZABORT | ZCAN | ZFERR => {
+                self.finished = true;
+                self.file = None;
+                self.status.fail("Aborted by sender");
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn handle_data(&mut self, payload: Vec<u8>, end: u8, now: Instant) -> Vec<u8> {
+        match self.expecting {
+            Expecting::FileInfo => {
+                self.expecting = Expecting::Nothing;
+                self.open_file(&payload, now)
+            }
+            Expecting::SessionInit => {
+                self.expecting = Expecting::Nothing;
+                self.send(hex_header(ZACK, [0; 4]), now)
+            }
+            Expecting::FileData => {
+                if let Some(file) = &mut self.file {
+                    if let Err(e) = file.write_all(&payload) {
+                        return self.fail(format!("Cannot write file: {e}"));
+                    }
+                }
+                self.position += payload.len() as u64;
+                self.status.bytes = self.position;
+                self.status.blocks += 1;
+                self.deadline = now + TIMEOUT;
+
+                if matches!(end, ZCRCE | ZCRCW) {
+                    self.expecting = Expecting::Nothing;
+                }
+                if matches!(end, ZCRCQ | ZCRCW) {
+                    return self.send(hex_header(ZACK, position(self.position)), now);
+                }
+                Vec::new()
+            }
+            Expecting::Nothing => Vec::new(),
+        }
+    }
+}
+
+impl FileTransfer for ZmodemReceiver {
+    fn start(&mut self, now: Instant) -> Vec<u8> {
+        self.send(Self::receiver_init(), now)
+    }
+
+    fn on_data(&mut self, data: &[u8], now: Instant) -> Vec<u8> {
+        let mut reply = Vec::new();
+        for event in self.parser.feed(data) {
+            if self.finished {
+                break;
+            }
+            match event {
+                Event::Header(header) => reply.extend(self.handle_header(header, now)),
+                Event::Data { payload, end } => reply.extend(self.handle_data(payload, end, now)),
+                Event::BadHeader => self.status.error("Corrupt header"),
+                Event::BadData => {
+                    self.status.error("CRC error in data");
+                    if self.expecting == Expecting::FileData {
+                        self.expecting = Expecting::Nothing;
+                        reply.extend(self.send(hex_header(ZRPOS, position(self.position)), now));
+                    }
+                }
+                Event::Cancel => {
+                    self.finished = true;
+                    self.file = None;
+                    self.status.state = TransferState::Cancelled;
+                    self.status.last_error = Some("Cancelled by sender".to_string());
+                }
+            }
+        }
+        reply
+    }
+
+    fn poll(&mut self, now: Instant) -> Vec<u8> {
+        if self.finished || now < self.deadline {
+            return Vec::new();
+        }
+        self.attempts += 1;
+        self.status.retries += 1;
+        if self.attempts > MAX_RETRIES {
+            return self.fail("Timed out waiting for the sender".to_string());
+        }
+        self.deadline = now + TIMEOUT;
+        if self.file.is_some() {
+            self.expecting = Expecting::Nothing;
+            return hex_header(ZRPOS, position(self.position));
+        }
+        self.last_packet.clone()
+    }
+
+    fn cancel(&mut self) -> Vec<u8> {
+        self.finished = true;
+        self.file = None;
+        self.status.state = TransferState::Cancelled;
+        cancel_sequence()
+    }
+
+    fn status(&self) -> &TransferStatus {
+        &self.status
+    }
+
+    fn trailer(&self) -> &[u8] {
+        // The sender answers our ZFIN with "OO" (over and out)
+        if self.status.state == TransferState::Done {
+            b"OO"
+        } else {
+            &[]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfer::tests::{loopback, sample_data, temp_dir};
+
+    fn send_file(data: &[u8], name: &str) -> (ZmodemSender, ZmodemReceiver, PathBuf) {
+        let dir = temp_dir(name);
+        let source = dir.join("source");
+        std::fs::create_dir(&source).unwrap();
+        let path = source.join("file.bin");
+        std::fs::write(&path, data).unwrap();
+
+        let mut sender = ZmodemSender::from_file(path.to_str().unwrap(), false).unwrap();
+        let mut receiver = ZmodemReceiver::new(dir.to_str().unwrap());
+        loopback(&mut sender, &mut receiver);
+        (sender, receiver, dir.join("file.bin"))
+    }
+
+    #[test]
+    fn loopback_file() {
+        // Several windows, so ZCRCW/ZACK are exercised
+        let data = sample_data(3 * WINDOW_SIZE + 100);
+        let (sender, receiver, path) = send_file(&data, "zmodem");
+        assert_eq!(sender.status().state, TransferState::Done);
+        assert_eq!(receiver.status().state, TransferState::Done);
+        assert_eq!(receiver.status().errors, 0);
+        assert_eq!(std::fs::read(path).unwrap(), data);
+    }
+
+    #[test]
+    fn loopback_empty_file() {
+        let (sender, receiver, path) = send_file(&[], "zmodem-empty");
+        assert_eq!(sender.status().state, TransferState::Done);
+        assert_eq!(receiver.status().state, TransferState::Done);
+        assert!(std::fs::read(path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn finished_receiver_expects_over_and_out() {
+        let (_, receiver, _) = send_file(b"data", "zmodem-oo");
+        assert_eq!(receiver.trailer(), b"OO");
+    }
+
+    #[test]
+    fn resume_partial_file() {
+        let data = sample_data(5000);
+        let dir = temp_dir("zmodem-resume");
+        std::fs::write(dir.join("file.bin"), &data[..1234]).unwrap();
+        let source = dir.join("source");
+        std::fs::create_dir(&source).unwrap();
+        let path = source.join("file.bin");
+        std::fs::write(&path, &data).unwrap();
+
+        let mut sender = ZmodemSender::from_file(path.to_str().unwrap(), false).unwrap();
+        let mut receiver = ZmodemReceiver::new(dir.to_str().unwrap());
+        loopback(&mut sender, &mut receiver);
+        assert_eq!(receiver.status().state, TransferState::Done);
+        assert_eq!(std::fs::read(dir.join("file.bin")).unwrap(), data);
+    }
+}